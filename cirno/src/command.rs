@@ -1,4 +1,4 @@
-use crate::{CirnoState, open, try_to, bar, error::CirnoError, terminal::{EventResult, backspace, clear_all, move_to, read_line}};
+use crate::{CirnoState, open, try_to, bar, erc, error::CirnoError, modes::list, project::Modes, search, terminal::{EventResult, backspace, clear_all, move_to, read_line}};
 use std::collections::HashMap;
 use std::io::stdout;
 use std::path::PathBuf;
@@ -9,9 +9,14 @@ use enum_dispatch::enum_dispatch;
 #[derive(Clone, Debug)]
 #[enum_dispatch]
 pub enum CommandEnum {
+  Erc(Erc),
+  ForceQuit(ForceQuit),
   Open(Open),
   Quit(Quit),
+  Results(Results),
   Splash(Splash),
+  Write(Write),
+  WriteQuit(WriteQuit),
 }
 
 // impl Debug for CommandEnum {
@@ -35,9 +40,14 @@ pub trait Command {
 /// This is called each time an instance of CirnoState is created.
 pub fn get_all_commands() -> HashMap<String, fn(Vec<String>) -> CommandEnum> {
   HashMap::from([
+    ("erc".to_string(), (|args| CommandEnum::Erc(Erc(args))) as fn(Vec<String>) -> CommandEnum),
     ("open".to_string(), (|args| CommandEnum::Open(Open(args))) as fn(Vec<String>) -> CommandEnum),
     ("q".to_string(), (|args| CommandEnum::Quit(Quit(args))) as fn(Vec<String>) -> CommandEnum),
+    ("q!".to_string(), (|args| CommandEnum::ForceQuit(ForceQuit(args))) as fn(Vec<String>) -> CommandEnum),
+    ("results".to_string(), (|args| CommandEnum::Results(Results(args))) as fn(Vec<String>) -> CommandEnum),
     ("splash".to_string(), (|args| CommandEnum::Splash(Splash(args))) as fn(Vec<String>) -> CommandEnum),
+    ("w".to_string(), (|args| CommandEnum::Write(Write(args))) as fn(Vec<String>) -> CommandEnum),
+    ("wq".to_string(), (|args| CommandEnum::WriteQuit(WriteQuit(args))) as fn(Vec<String>) -> CommandEnum),
   ])
 }
 
@@ -46,18 +56,46 @@ pub fn read_from_bar(state: &mut CirnoState) -> Result<EventResult, anyhow::Erro
   bar::message(":".to_string(), state)?;
   let line = read_line()?;
   // remove the colon if the command comes back empty
-  if line.eq("") {
+  if line.is_empty() {
     backspace()?;
     return Ok(EventResult::Drop);
-  // TODO: matches some extra things, e.g. `splashhhhh` for `splash`
-  } else if let Some(key) = state.commands.keys().find(|&k| line.starts_with(k)) {
-    let cmd = state.commands.get(key).unwrap();
-    // split line by spaces and remove the first item to yield just the arguments
-    let mut args: Vec<String> = line.split(' ').collect::<Vec<&str>>().iter().map(|l| l.to_string()).collect();
-    args.remove(0);
-    return (cmd)(args).execute(state);
-  } else {
+  }
+  // split line by spaces; the first item is the command, and the rest are the arguments
+  let mut args: Vec<String> = line.split(' ').filter(|l| !l.is_empty()).map(|l| l.to_string()).collect();
+  let Some(name) = args.first().cloned() else {
+    backspace()?;
+    return Ok(EventResult::Drop)
+  };
+  args.remove(0);
+  let Some(cmd) = state.commands.get(&name) else {
     return Err(CirnoError::InvalidCommand(line).into());
+  };
+  (cmd)(args).execute(state)
+}
+
+/// A command to check the open project against the electrical rules, listing the results.
+/// Arguments: 0
+#[derive(Clone, Debug)]
+pub struct Erc(pub Vec<String>);
+
+impl Command for Erc {
+  fn execute(&self, state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+    let [] = self.0.as_slice() else {
+      return Err(CirnoError::ArgumentError(0, self.0.len()).into());
+    };
+    if state.project.is_none() {
+      return Err(CirnoError::NoProjectOpen.into());
+    }
+    if !matches!(state.mode, Modes::Normal) {
+      return Err(CirnoError::NotInNormalMode("erc".to_string()).into());
+    }
+    state.erc = erc::Erc { results: erc::check(state) };
+    if state.erc.results.is_empty() {
+      bar::message("no problems found".to_string(), state)?;
+      return Ok(EventResult::Ok)
+    }
+    list::open(erc::lines(&state.erc.results), erc::close, state)?;
+    Ok(EventResult::Ok)
   }
 }
 
@@ -77,11 +115,29 @@ impl Command for Open {
 }
 
 /// A command to quit cirno.
+/// Fails if there are unsaved changes.
 /// Arguments: 0
 #[derive(Clone, Debug)]
 pub struct Quit(pub Vec<String>);
 
 impl Command for Quit {
+  fn execute(&self, state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+    let [] = self.0.as_slice() else {
+      return Err(CirnoError::ArgumentError(0, self.0.len()).into());
+    };
+    if state.dirty {
+      return Err(CirnoError::UnsavedChanges.into());
+    }
+    Ok(EventResult::Exit)
+  }
+}
+
+/// A command to quit cirno, discarding any unsaved changes.
+/// Arguments: 0
+#[derive(Clone, Debug)]
+pub struct ForceQuit(pub Vec<String>);
+
+impl Command for ForceQuit {
   fn execute(&self, _state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
     let [] = self.0.as_slice() else {
       return Err(CirnoError::ArgumentError(0, self.0.len()).into());
@@ -90,6 +146,30 @@ impl Command for Quit {
   }
 }
 
+/// A command to list the results of the last search, to pick one to jump to.
+/// Arguments: 0
+#[derive(Clone, Debug)]
+pub struct Results(pub Vec<String>);
+
+impl Command for Results {
+  fn execute(&self, state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+    let [] = self.0.as_slice() else {
+      return Err(CirnoError::ArgumentError(0, self.0.len()).into());
+    };
+    if state.project.is_none() {
+      return Err(CirnoError::NoProjectOpen.into());
+    }
+    if !matches!(state.mode, Modes::Normal) {
+      return Err(CirnoError::NotInNormalMode("results".to_string()).into());
+    }
+    if state.search_result.borrow().is_empty() {
+      return Err(CirnoError::NoResultsFound.into());
+    }
+    list::open(search::lines(state)?, search::close, state)?;
+    Ok(EventResult::Ok)
+  }
+}
+
 /// A command to render cirno's splash screen.
 /// Arguments: 0
 #[derive(Clone, Debug)]
@@ -119,3 +199,36 @@ impl Command for Splash {
     Ok(EventResult::Ok)
   }
 }
+
+/// A command to write the open project to disk, optionally to a different path.
+/// Arguments: 0 or 1
+#[derive(Clone, Debug)]
+pub struct Write(pub Vec<String>);
+
+impl Command for Write {
+  fn execute(&self, state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+    let path = match self.0.as_slice() {
+      [] => None,
+      [filename] => Some(PathBuf::from(filename)),
+      _ => return Err(CirnoError::ArgumentError(1, self.0.len()).into()),
+    };
+    let path = state.write(path)?;
+    bar::message(format!("\"{}\" written", path.display()), state)?;
+    Ok(EventResult::Ok)
+  }
+}
+
+/// A command to write the open project to disk and quit cirno.
+/// Arguments: 0
+#[derive(Clone, Debug)]
+pub struct WriteQuit(pub Vec<String>);
+
+impl Command for WriteQuit {
+  fn execute(&self, state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+    let [] = self.0.as_slice() else {
+      return Err(CirnoError::ArgumentError(0, self.0.len()).into());
+    };
+    state.write(None)?;
+    Ok(EventResult::Exit)
+  }
+}
@@ -115,12 +115,14 @@ pub fn report(state: &mut CirnoState) -> Result<(), anyhow::Error> {
       (u_char, u_color) = object.get_char(cursor_region.position - region.position).unwrap();
     }
   }
-  if wire.is_some() {
-    let wire = wire.unwrap();
+  if let Some(wire) = wire {
     report = format!("({}) {}", wire.label, report);
     color = wire.color;
     (u_char, u_color) = wire.get_char(Vector2 { x: 0, y: 0 }).unwrap();
   }
+  if let Some(id) = state.netlist.net_at(state.cursor) {
+    report = format!("{} [net {}]", report, id);
+  }
   // set state.char_under_cursor
   state.char_under_cursor = (u_char, u_color);
   // print report
@@ -150,8 +152,7 @@ pub fn debug(state: &CirnoState) -> Result<(), anyhow::Error> {
       logger::debug(format!("{:?} -> {:?}", state.cursor, object));
     }
   }
-  if wire.is_some() {
-    let wire = wire.unwrap();
+  if let Some(wire) = wire {
     logger::debug(format!("w: {:?}", wire));
   }
   Ok(())
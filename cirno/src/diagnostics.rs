@@ -0,0 +1,156 @@
+use crate::{error::CirnoError, logger, project::{Span, Vector2}};
+use std::fmt::Display;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+  Error,
+  Warning,
+}
+
+#[derive(Clone, Debug)]
+// a problem found while loading a project, tied to the place it came from
+pub struct Diagnostic {
+  pub severity: Severity,
+  pub span: Span,
+  pub message: String,
+  // where on the board the problem is, if it has a place there
+  pub position: Option<Vector2>,
+}
+
+impl Display for Diagnostic {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+    // problems with objects added while editing have no place in a file to point to
+    if self.span.file.is_empty() {
+      return f.write_str(&self.message)
+    }
+    write!(f, "{}: {}", self.span, self.message)
+  }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Diagnostics {
+  pub items: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+  /// Add a diagnostic with the given severity.
+  pub fn push<M: Display>(&mut self, severity: Severity, span: &Span, message: M) {
+    self.items.push(Diagnostic { severity, span: span.clone(), message: message.to_string(), position: None });
+  }
+  /// Add a diagnostic with the given severity, at a position on the board.
+  pub fn push_at<M: Display>(&mut self, severity: Severity, position: Vector2, message: M) {
+    self.items.push(Diagnostic { severity, span: Span::default(), message: message.to_string(), position: Some(position) });
+  }
+  /// Add an error.
+  pub fn error<M: Display>(&mut self, span: &Span, message: M) {
+    self.push(Severity::Error, span, message);
+  }
+  /// Add a warning.
+  pub fn warn<M: Display>(&mut self, span: &Span, message: M) {
+    self.push(Severity::Warning, span, message);
+  }
+  /// Add an error if `result` is one, returning the value otherwise.
+  pub fn check<T>(&mut self, span: &Span, result: Result<T, CirnoError>) -> Option<T> {
+    match result {
+      Ok(v) => Some(v),
+      Err(e) => {
+        self.error(span, e);
+        None
+      },
+    }
+  }
+  /// Return the number of diagnostics with the given severity.
+  pub fn count(&self, severity: Severity) -> usize {
+    self.items.iter().filter(|x| x.severity == severity).count()
+  }
+  /// Return whether any diagnostic is an error.
+  pub fn has_errors(&self) -> bool {
+    self.count(Severity::Error) > 0
+  }
+  /// Log every diagnostic.
+  pub fn log(&self) {
+    for diagnostic in self.items.iter() {
+      match diagnostic.severity {
+        Severity::Error => logger::error(diagnostic.to_string()),
+        Severity::Warning => logger::warn(diagnostic.to_string()),
+      }
+    }
+  }
+  /// Log every diagnostic, then return an error if any of them are errors.
+  /// A lone error is returned as-is, and is left for the caller to log;
+  /// otherwise, the error summarizes them.
+  pub fn report(&self) -> Result<(), CirnoError> {
+    let errors = self.count(Severity::Error);
+    let warnings = self.count(Severity::Warning);
+    if errors == 1 && warnings == 0 {
+      return Err(CirnoError::Diagnostic(self.items[0].clone()))
+    }
+    self.log();
+    match errors {
+      0 => Ok(()),
+      _ => Err(CirnoError::Diagnostics(errors, warnings)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{CirnoState, error::CirnoError, parser, project::Span};
+  use super::{Diagnostics, Severity};
+
+  // where each diagnostic points, as shown to the user
+  fn places(diagnostics: &Diagnostics) -> Vec<String> {
+    diagnostics.items
+      .iter()
+      .map(|x| x.to_string().split(": ").next().unwrap().to_string())
+      .collect()
+  }
+
+  #[test]
+  fn spans() {
+    let mut diagnostics = Diagnostics::default();
+    parser::parse(": meta bounds 30 15\n\n  : wire colour red from 0 0 to 0 2\n: bogus\n", "test.cip", &mut diagnostics);
+    // lines and columns both count from 1
+    assert_eq!(places(&diagnostics), ["test.cip:3:10", "test.cip:4:3"]);
+    assert_eq!(diagnostics.items[0].to_string(), "test.cip:3:10: invalid attribute 'colour'");
+    // objects added while editing have no place in a file
+    assert_eq!(Span::default().to_string(), "<unsaved>");
+    let mut diagnostics = Diagnostics::default();
+    diagnostics.error(&Span::default(), CirnoError::NoPins);
+    assert_eq!(diagnostics.items[0].to_string(), CirnoError::NoPins.to_string());
+  }
+
+  #[test]
+  fn collects_in_order() {
+    let mut state = CirnoState::with_size(0, 0);
+    let mut diagnostics = Diagnostics::default();
+    let contents = "\
+: meta bounds 30 15
+: net type vcc y 40
+: wire color red from 0 0 to 0 40
+: wire color red from 50 0 to 0 2
+";
+    crate::load(&mut state, contents, "test.cip", &mut diagnostics).unwrap();
+    // every problem is kept, in the order of the objects they belong to
+    assert_eq!(places(&diagnostics), ["test.cip:2:1", "test.cip:3:1", "test.cip:4:1"]);
+    assert_eq!(diagnostics.count(Severity::Error), 3);
+    assert!(diagnostics.has_errors());
+    assert!(matches!(diagnostics.report(), Err(CirnoError::Diagnostics(3, 0))));
+  }
+
+  #[test]
+  fn severities() {
+    let span = Span::default();
+    let mut diagnostics = Diagnostics::default();
+    diagnostics.warn(&span, "first");
+    assert!(!diagnostics.has_errors());
+    assert!(diagnostics.report().is_ok());
+    diagnostics.error(&span, "second");
+    assert!(diagnostics.has_errors());
+    assert_eq!(diagnostics.count(Severity::Warning), 1);
+    assert!(matches!(diagnostics.report(), Err(CirnoError::Diagnostics(1, 1))));
+    // a lone error is returned as it is
+    diagnostics.items.remove(0);
+    assert!(matches!(diagnostics.report(), Err(CirnoError::Diagnostic(x)) if x.message == "second"));
+  }
+}
@@ -0,0 +1,204 @@
+use crate::{CirnoState, diagnostics::{Diagnostic, Diagnostics, Severity}, modes::list::Line, project::{Direction, Modes, ObjectEnum, Pin, Value}};
+use crossterm::style::Color;
+
+#[derive(Clone, Debug, Default)]
+// the results of the last electrical rule check
+pub struct Erc {
+  pub results: Vec<Diagnostic>,
+}
+
+/// Run every electrical rule over the pins and nets of a simulated board.
+/// The results are sorted by severity, then by their position in reading order.
+pub fn check(state: &CirnoState) -> Vec<Diagnostic> {
+  let mut diagnostics = Diagnostics::default();
+  let objects = state.objects.borrow();
+  let pins: Vec<&Pin> = objects
+    .iter()
+    .filter_map(|x| match x {
+      ObjectEnum::Pin(pin) => Some(pin),
+      _ => None,
+    })
+    .collect();
+  for pin in pins.iter() {
+    let position = pin.region.position;
+    let describe = format!("{} at ({}, {})", pin.describe(), position.x, position.y);
+    let connected = state.netlist.is_connected(position);
+    let rail = state.netlist.net_at(position).and_then(|id| state.netlist.rail(id));
+    match pin.direction() {
+      Direction::Input if !connected => {
+        diagnostics.push_at(Severity::Warning, position, format!("input {} is not connected", describe));
+      },
+      Direction::Output if !connected => {
+        diagnostics.push_at(Severity::Warning, position, format!("output {} is not connected to anything", describe));
+      },
+      Direction::Output => {
+        let Some(limit) = pin.family.fan_out() else { continue };
+        let net = state.netlist.net_at(position);
+        let loads = pins
+          .iter()
+          .filter(|x| x.direction() == Direction::Input && state.netlist.net_at(x.region.position) == net)
+          .count();
+        if loads > limit {
+          diagnostics.push_at(Severity::Warning, position, format!("output {} drives {} inputs, more than its limit of {}", describe, loads, limit));
+        }
+      },
+      Direction::Power => {
+        let t = if matches!(pin.value, Value::Vcc) { "vcc" } else { "gnd" };
+        if rail != Some(t) {
+          diagnostics.push_at(Severity::Error, position, format!("{} is not powered by the {} rail", describe, t));
+        }
+      },
+      Direction::Nc if connected => {
+        diagnostics.push_at(Severity::Warning, position, format!("{} is not connected internally, but is wired to {}", describe, rail.unwrap_or("a net")));
+      },
+      _ => {},
+    }
+  }
+  let mut results = diagnostics.items;
+  results.sort_by_key(|x| {
+    let position = x.position.unwrap_or_default();
+    (x.severity == Severity::Warning, position.y, position.x)
+  });
+  results
+}
+
+/// Return the results of a check as lines of text, grouped by severity under a heading
+/// for each group, along with the index and color of the result on each other line.
+pub fn lines(results: &[Diagnostic]) -> Vec<Line> {
+  let mut lines: Vec<Line> = vec![];
+  for (severity, name, color) in [(Severity::Error, "errors", Color::Red), (Severity::Warning, "warnings", Color::Yellow)] {
+    let group: Vec<(usize, &Diagnostic)> = results.iter().enumerate().filter(|(_, x)| x.severity == severity).collect();
+    if group.is_empty() {
+      continue
+    }
+    lines.push((format!("{} ({})", name, group.len()), None));
+    lines.extend(group.iter().map(|(i, x)| (format!("  {}", x), Some((*i, color)))));
+  }
+  lines
+}
+
+/// Go back to the board from the list of results, moving the cursor to the one picked.
+pub fn close(selected: Option<usize>, state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  if let Some(position) = selected.and_then(|i| state.erc.results.get(i)).and_then(|x| x.position) {
+    state.cursor = position;
+  }
+  state.set_mode(Modes::Normal)
+}
+
+/// Return the results of a check as lines of text, grouped by severity.
+pub fn summary(results: &[Diagnostic]) -> Vec<String> {
+  lines(results).into_iter().map(|(line, _)| line).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{CirnoState, diagnostics::{Diagnostic, Diagnostics, Severity}, project::Vector2};
+
+  const BOARD: &str = "\
+: meta bounds 40 8
+: net type vcc y 0
+: net type gnd y 1
+";
+
+  // a chip at (x, 2), with its vcc pin wired to the vcc rail and its gnd pin to the gnd rail
+  fn powered(t: &str, x: u16) -> String {
+    format!(": chip type ls0xx/{} pos {} 2\n: wire color red from {} 0 to {} 2\n: wire color blue from {} 1 to {} 4\n", t, x, x, x, x + 6, x + 6)
+  }
+
+  fn wire(from: (u16, u16), to: (u16, u16)) -> String {
+    format!(": wire color green from {} {} to {} {}\n", from.0, from.1, to.0, to.1)
+  }
+
+  fn check(contents: &str) -> Vec<Diagnostic> {
+    let mut state = CirnoState::with_size(0, 0);
+    let mut diagnostics = Diagnostics::default();
+    crate::load(&mut state, contents, "test.cip", &mut diagnostics).unwrap();
+    state.rebuild_simulation(&mut diagnostics).unwrap();
+    assert!(!diagnostics.has_errors(), "{:?}", diagnostics.items);
+    super::check(&state)
+  }
+
+  // the result at a position, if there is one
+  fn at(results: &[Diagnostic], x: u16, y: u16) -> Option<(Severity, &str)> {
+    results
+      .iter()
+      .find(|r| r.position == Some(Vector2 { x, y }))
+      .map(|r| (r.severity, r.message.as_str()))
+  }
+
+  #[test]
+  fn unconnected_input() {
+    let board = format!("{}{}", BOARD, powered("sn74ls04", 0));
+    let results = check(&board);
+    assert_eq!(at(&results, 0, 4), Some((Severity::Warning, "input U1 pin 1 '1a at (0, 4) is not connected")));
+    let results = check(&format!("{}{}", board, wire((0, 0), (0, 4))));
+    assert_eq!(at(&results, 0, 4), None);
+  }
+
+  #[test]
+  fn unconnected_output() {
+    let board = format!("{}{}", BOARD, powered("sn74ls04", 0));
+    let results = check(&board);
+    assert_eq!(at(&results, 1, 4), Some((Severity::Warning, "output U1 pin 2 '1y at (1, 4) is not connected to anything")));
+    let results = check(&format!("{}{}", board, wire((1, 4), (2, 4))));
+    assert_eq!(at(&results, 1, 4), None);
+  }
+
+  #[test]
+  fn fan_out() {
+    // the inputs of four inverters, other than the one driving the output, in the order
+    // they are chained to the first output
+    let inputs: Vec<(u16, u16)> = (0..4)
+      .flat_map(|chip| [(0, 4), (2, 4), (4, 4), (5, 2), (3, 2), (1, 2)].map(|(x, y)| (chip * 8 + x, y)))
+      .skip(1)
+      .collect();
+    let board: String = [BOARD.to_string()]
+      .into_iter()
+      .chain((0..4).map(|chip| powered("sn74ls04", chip * 8)))
+      .collect();
+    let drive = |loads: usize| {
+      let mut contents = board.clone();
+      let mut from = (1, 4);
+      for to in inputs.iter().take(loads) {
+        contents.push_str(&wire(from, *to));
+        from = *to;
+      }
+      check(&contents)
+    };
+    assert_eq!(at(&drive(20), 1, 4), None);
+    let message = "output U1 pin 2 '1y at (1, 4) drives 21 inputs, more than its limit of 20";
+    assert_eq!(at(&drive(21), 1, 4), Some((Severity::Warning, message)));
+  }
+
+  #[test]
+  fn power_on_wrong_rail() {
+    let board = format!(
+      "{}: chip type ls0xx/sn74ls04 pos 0 2\n{}{}",
+      BOARD,
+      wire((0, 1), (0, 2)),
+      wire((6, 0), (6, 4)),
+    );
+    let results = check(&board);
+    // errors come first, then warnings, each in reading order
+    assert_eq!(at(&results, 0, 2), Some((Severity::Error, "U1 pin 14 vcc at (0, 2) is not powered by the vcc rail")));
+    assert_eq!(at(&results, 6, 4), Some((Severity::Error, "U1 pin 7 gnd at (6, 4) is not powered by the gnd rail")));
+    let order: Vec<(bool, u16, u16)> = results
+      .iter()
+      .map(|x| {
+        let position = x.position.unwrap();
+        (x.severity == Severity::Warning, position.y, position.x)
+      })
+      .collect();
+    assert_eq!(order[..2], [(false, 2, 0), (false, 4, 6)]);
+    assert!(order[2..].iter().all(|x| x.0));
+    assert!(order.windows(2).all(|x| x[0] < x[1]));
+  }
+
+  #[test]
+  fn connected_nc_pin() {
+    let board = format!("{}{}", BOARD, powered("sn74ls20", 0));
+    assert_eq!(at(&check(&board), 2, 4), None);
+    let results = check(&format!("{}{}", board, wire((2, 0), (2, 4))));
+    assert_eq!(at(&results, 2, 4), Some((Severity::Warning, "U1 pin 3 nc at (2, 4) is not connected internally, but is wired to vcc")));
+  }
+}
@@ -1,12 +1,28 @@
-use crate::{CirnoState, logger, parser::Token, project::Attribute};
+use crate::{CirnoState, diagnostics::Diagnostic, logger, parser::Token, project::{Attribute, Package, Span}};
 use std::io::stdout;
 use std::fmt::Display;
 use crossterm::execute;
 
 #[derive(thiserror::Error, Debug)]
 pub enum CirnoError {
+  #[error("{0}")]
+  Diagnostic(Diagnostic),
+  #[error("{0} errors and {1} warnings found (press C to view)")]
+  Diagnostics(usize, usize),
   #[error("expected {0} arguments, got {1}")]
   ArgumentError(u16, usize),
+  #[error("chips must straddle the gap at row {0}")]
+  ChipNotStraddlingGap(u16),
+  #[error("pin direction '{0}' does not match its value")]
+  DirectionMismatch(String),
+  #[error("designator '{0}' is already used by the chip at {1}")]
+  DuplicateDesignator(String, Span),
+  #[error("pin number {0} is used more than once")]
+  DuplicatePinNumber(u16),
+  #[error("header names '{0}', but the file defines '{1}'")]
+  HeaderNameMismatch(String, String),
+  #[error("headers may only appear in chip definitions")]
+  HeaderOutsideChip,
   #[error("invalid attribute '{0}'")]
   InvalidAttribute(String),
   #[error("attribute '{0}' is invalid for {1} objects")]
@@ -21,34 +37,64 @@ pub enum CirnoError {
   InvalidFiletype(String),
   #[error("invalid object type '{0}'")]
   InvalidObjectType(String),
+  #[error("chip definitions may only contain pin objects")]
+  InvalidObjectTypeForChip,
+  #[error("invalid pattern '{0}'")]
+  InvalidPattern(String),
   #[error("invalid search")]
   InvalidSearch,
+  #[error("invalid option '{0}' for a sequential value")]
+  InvalidSequentialOption(String),
+  #[error("invalid table row '{0}'")]
+  InvalidTableRow(String),
   #[error("invalid value attribute: {0}")]
   InvalidValueAttribute(String),
   #[error("invalid wire")]
   InvalidWire,
   #[error("invalid value '{0}' for attribute '{1}'")]
   InvalidValueForAttribute(String, String),
+  #[error("a chip definition may only have one header, before its pins")]
+  MisplacedHeader,
   #[error("meta object missing or invalid")]
   MetaObjectError,
   #[error("missing attribute: {0}")]
   MissingAttribute(String),
+  #[error("pin numbers must be given for every pin or none")]
+  MissingPinNumber,
   #[error("invalid value for attribute '{0}'")]
   NamelessInvalidValueForAttribute(String),
-  #[error("'{0}' not found in stdlib")]
-  NotFoundInStdlib(String),
-  #[error("opening .cic files is not yet implemented")]
-  OpenCicNotImplemented,
+  #[error("'{0}' not found in {1}")]
+  NotFoundInStdlib(String, String),
+  #[error(":{0} only works on a board in normal mode")]
+  NotInNormalMode(String),
+  #[error("chip definitions must have an even number of pins, got {0}")]
+  OddPinCount(usize),
+  #[error("only chips and wires can be moved")]
+  ObjectCannotBeMoved,
   #[error("object out of bounds")]
   OutOfBounds,
   #[error("expected {0}, got None")]
   OutOfTokens(Token),
   #[error("expected Number, got None")]
   OutOfTokensExpectedNumber,
-  #[error("regions {0} and {1} are overlapping")]
-  OverlappingRegion(usize, usize),
+  #[error("package {0} does not have {1} pins")]
+  PackageMismatch(Package, usize),
+  #[error("pin number {0} is out of range for {1} pins")]
+  PinNumberOutOfRange(u16, usize),
+  #[error("region overlaps with the region at {0}")]
+  OverlappingRegion(Span),
+  #[error("nothing to {0} under the cursor")]
+  NoObjectAtCursor(String),
+  #[error("net at row {0} is inside a terminal strip")]
+  NetInTerminalStrip(u16),
+  #[error("chip definitions must have at least one pair of pins")]
+  NoPins,
+  #[error("no project open")]
+  NoProjectOpen,
   #[error("no results found")]
   NoResultsFound,
+  #[error("terminal strips of height {1} around the gap at row {0} do not fit within the bounds")]
+  StripsOutOfBounds(u16, u16),
   #[error("terminal too small")]
   TerminalTooSmall,
   #[error("cannot repeat action more than 1000 times")]
@@ -59,8 +105,10 @@ pub enum CirnoError {
   UnexpectedToken(Token, Token),
   #[error("expected Number, got {0}")]
   UnexpectedTokenExpectedNumber(Token),
-  #[error("unrecognized token")] // TODO: add a field
-  UnrecognizedToken,
+  #[error("no write since last change (add ! to override)")]
+  UnsavedChanges,
+  #[error("unrecognized token '{0}'")]
+  UnrecognizedToken(String),
 }
 
 /// Call a function, possibly producing a recoverable error message as a side effect.
@@ -0,0 +1,46 @@
+use crate::{diagnostics::Diagnostics, project::ObjectEnum, terminal::{clear_all, move_within_bounds}, CirnoState};
+use std::io::stdout;
+use crossterm::{execute, style::{Color, Colors}};
+
+/// Redraw the board, then `state.ghost` on top of it.
+pub fn redraw(skip: Option<usize>, state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  clear_all()?;
+  state.render()?;
+  render(skip, state)
+}
+
+/// Draw `state.ghost`, the preview of an object being placed, on top of the board.
+/// It is drawn in red if it could not be placed where it is, ignoring the object in
+/// `source` at index `skip`.
+pub fn render(skip: Option<usize>, state: &CirnoState) -> Result<(), anyhow::Error> {
+  let Some(ghost) = state.ghost.as_ref() else { return Ok(()) };
+  let mut diagnostics = Diagnostics::default();
+  state.verify_placement(ghost, skip, &mut diagnostics);
+  let valid = !diagnostics.has_errors();
+  match ghost {
+    ObjectEnum::Chip(chip) => {
+      let color = if valid { Color::DarkGrey } else { Color::Red };
+      execute!(stdout(), crossterm::style::SetForegroundColor(color))?;
+      for object in state.cic_data.get(&chip.t).unwrap().iter() {
+        let ObjectEnum::Pin(pin) = object else { continue };
+        let position = chip.region.position + pin.region.position;
+        if position.x >= state.meta.bounds.x || position.y >= state.meta.bounds.y {
+          continue;
+        }
+        move_within_bounds(position.x, position.y, state)?;
+        execute!(stdout(), crossterm::style::Print("."))?;
+      }
+    },
+    ObjectEnum::Wire(wire) => {
+      let background = if valid { Color::Reset } else { Color::Red };
+      execute!(stdout(), crossterm::style::SetColors(Colors::new(wire.color, background)))?;
+      for end in [wire.from, wire.to] {
+        move_within_bounds(end.x, end.y, state)?;
+        execute!(stdout(), crossterm::style::Print("*"))?;
+      }
+    },
+    _ => {},
+  }
+  execute!(stdout(), crossterm::style::ResetColor)?;
+  Ok(())
+}
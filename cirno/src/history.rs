@@ -0,0 +1,245 @@
+use crate::project::{ObjectEnum, SequentialState};
+use std::collections::HashMap;
+
+/// A single change to the objects in `source`, by index.
+#[derive(Clone, Debug)]
+pub enum Edit {
+  Insert(usize, ObjectEnum),
+  Delete(usize, ObjectEnum),
+  // the object before and after
+  Move(usize, ObjectEnum, ObjectEnum),
+  Change(usize, ObjectEnum, ObjectEnum),
+}
+
+impl Edit {
+  /// Make this change to `source`.
+  pub fn apply(&self, source: &mut Vec<ObjectEnum>) {
+    match self {
+      Edit::Insert(index, object) => source.insert(*index, object.clone()),
+      Edit::Delete(index, _) => { source.remove(*index); },
+      Edit::Move(index, _, after) | Edit::Change(index, _, after) => source[*index] = after.clone(),
+    }
+  }
+  /// Return the change that undoes this one.
+  pub fn inverse(&self) -> Edit {
+    match self.clone() {
+      Edit::Insert(index, object) => Edit::Delete(index, object),
+      Edit::Delete(index, object) => Edit::Insert(index, object),
+      Edit::Move(index, before, after) => Edit::Move(index, after, before),
+      Edit::Change(index, before, after) => Edit::Change(index, after, before),
+    }
+  }
+  /// Return what the states saved before this change are keyed by once it is made, since
+  /// inserting or deleting an object moves every object after it in `source`.
+  pub fn shift(&self, states: &SavedStates) -> SavedStates {
+    states
+      .iter()
+      .filter_map(|((chip, label), state)| {
+        let chip = match (self, *chip) {
+          (_, None) => None,
+          (Edit::Insert(index, _), Some(x)) if x >= *index => Some(x + 1),
+          (Edit::Delete(index, _), Some(x)) if x == *index => return None,
+          (Edit::Delete(index, _), Some(x)) if x > *index => Some(x - 1),
+          (_, x) => x,
+        };
+        Some(((chip, label.clone()), state.clone()))
+      })
+      .collect()
+  }
+  /// Return a short description of this change, e.g. `delete wire`.
+  pub fn describe(&self) -> String {
+    let (verb, object) = match self {
+      Edit::Insert(_, object) => ("insert", object),
+      Edit::Delete(_, object) => ("delete", object),
+      Edit::Move(_, object, _) => ("move", object),
+      Edit::Change(_, object, _) => ("change", object),
+    };
+    let kind = match object {
+      ObjectEnum::Chip(_) => "chip",
+      ObjectEnum::Header(_) => "header",
+      ObjectEnum::Meta(_) => "meta",
+      ObjectEnum::Net(_) => "net",
+      ObjectEnum::Pin(_) => "pin",
+      ObjectEnum::Wire(_) => "wire",
+    };
+    format!("{} {}", verb, kind)
+  }
+}
+
+/// What each sequential pin stores, by the index in `source` of its chip and its label in
+/// the chip definition, or by its label alone if it is not on a chip.
+pub type SavedStates = HashMap<(Option<usize>, String), SequentialState>;
+
+/// An edit, along with what each sequential pin stored just before it was made.
+/// Everything else is derived from `source`, so it is simulated again on undo and redo.
+#[derive(Clone, Debug)]
+pub struct Entry {
+  pub edit: Edit,
+  pub states: SavedStates,
+}
+
+#[derive(Clone, Debug)]
+pub struct History {
+  pub undo: Vec<Entry>,
+  pub redo: Vec<Entry>,
+  // the number of edits in `undo` when the project was last written, or None if those
+  // edits were undone and then replaced
+  pub saved: Option<usize>,
+}
+
+impl Default for History {
+  fn default() -> History {
+    History { undo: vec![], redo: vec![], saved: Some(0) }
+  }
+}
+
+impl History {
+  /// Record an edit that has just been made, forgetting anything that was undone.
+  pub fn record(&mut self, entry: Entry) {
+    if self.saved.is_some_and(|x| x > self.undo.len()) {
+      self.saved = None;
+    }
+    self.undo.push(entry);
+    self.redo.clear();
+  }
+  /// Remember that the project was written with every edit in `undo` made.
+  pub fn mark_saved(&mut self) {
+    self.saved = Some(self.undo.len());
+  }
+  /// Return true if undoing or redoing has led back to the project as it was last written.
+  pub fn is_saved(&self) -> bool {
+    self.saved == Some(self.undo.len())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{CirnoState, diagnostics::Diagnostics, project::{ObjectEnum, Vector2, Voltage, Wire}};
+  use super::Edit;
+
+  const PROJECT: &str = "\
+: meta bounds 30 15
+: net type vcc y 0
+: net type gnd y 1
+: chip type ls0xx/sn74ls04 pos 0 2
+: chip type ls0xx/sn74ls74 pos 16 2
+: wire color red from 0 0 to 0 2
+";
+
+  fn load() -> CirnoState {
+    let mut state = CirnoState::with_size(0, 0);
+    let mut diagnostics = Diagnostics::default();
+    crate::load(&mut state, PROJECT, "test.cip", &mut diagnostics).unwrap();
+    state.rebuild_simulation(&mut diagnostics).unwrap();
+    assert!(!diagnostics.has_errors(), "{:?}", diagnostics.items);
+    state
+  }
+
+  fn wire(x: u16) -> ObjectEnum {
+    ObjectEnum::Wire(Wire { from: Vector2 { x, y: 1 }, to: Vector2 { x, y: 4 }, ..Default::default() })
+  }
+
+  fn wires(state: &CirnoState) -> usize {
+    state.source.borrow().iter().filter(|x| matches!(x, ObjectEnum::Wire(_))).count()
+  }
+
+  #[test]
+  fn insert_and_delete() {
+    let mut state = load();
+    state.insert_object(wire(6)).unwrap();
+    assert_eq!(wires(&state), 2);
+    assert!(state.dirty);
+    assert_eq!(state.undo().unwrap().as_deref(), Some("insert wire"));
+    assert_eq!(wires(&state), 1);
+    assert!(!state.dirty);
+    assert_eq!(state.redo().unwrap().as_deref(), Some("insert wire"));
+    assert_eq!(wires(&state), 2);
+    assert_eq!(state.redo().unwrap(), None);
+
+    let index = state.source.borrow().len() - 1;
+    state.delete_object(index).unwrap();
+    assert_eq!(wires(&state), 1);
+    // deleting forgets the insert that could be redone, not the one that was made
+    assert_eq!(state.undo().unwrap().as_deref(), Some("delete wire"));
+    assert_eq!(state.undo().unwrap().as_deref(), Some("insert wire"));
+    assert_eq!(state.undo().unwrap(), None);
+    assert_eq!(wires(&state), 1);
+  }
+
+  #[test]
+  fn move_is_undone() {
+    let mut state = load();
+    let index = state.source.borrow().iter().position(|x| matches!(x, ObjectEnum::Wire(_))).unwrap();
+    let before = state.source.borrow()[index].clone();
+    let ObjectEnum::Wire(mut after) = before.clone() else { unreachable!() };
+    after.from.x += 1;
+    after.to.x += 1;
+    state.apply_edit(Edit::Move(index, before, ObjectEnum::Wire(after))).unwrap();
+    let x = |state: &CirnoState| match &state.source.borrow()[index] {
+      ObjectEnum::Wire(wire) => wire.from.x,
+      _ => unreachable!(),
+    };
+    assert_eq!(x(&state), 1);
+    state.undo().unwrap();
+    assert_eq!(x(&state), 0);
+    state.redo().unwrap();
+    assert_eq!(x(&state), 1);
+  }
+
+  #[test]
+  fn failed_edit_is_not_recorded() {
+    let mut state = load();
+    let source = state.source.borrow().len();
+    // off the board
+    assert!(state.insert_object(wire(40)).is_err());
+    assert_eq!(state.source.borrow().len(), source);
+    assert!(state.history.undo.is_empty());
+    assert!(!state.dirty);
+  }
+
+  #[test]
+  fn short_is_kept() {
+    let mut state = load();
+    // the board can still be edited with a problem in the circuit, so it can be fixed
+    let short = ObjectEnum::Wire(Wire { from: Vector2 { x: 20, y: 0 }, to: Vector2 { x: 20, y: 1 }, ..Default::default() });
+    state.insert_object(short).unwrap();
+    assert!(state.diagnostics.has_errors());
+    assert!(!state.conflicts.is_empty());
+    assert_eq!(state.undo().unwrap().as_deref(), Some("insert wire"));
+    assert!(!state.diagnostics.has_errors());
+    assert!(state.conflicts.is_empty());
+  }
+
+  #[test]
+  fn saved_marker() {
+    let mut state = load();
+    state.insert_object(wire(6)).unwrap();
+    state.history.mark_saved();
+    state.dirty = false;
+    state.undo().unwrap();
+    assert!(state.dirty);
+    state.redo().unwrap();
+    assert!(!state.dirty);
+    // replacing the written edit means the project can no longer get back to it
+    state.undo().unwrap();
+    state.insert_object(wire(7)).unwrap();
+    state.undo().unwrap();
+    assert!(state.dirty);
+    assert_eq!(state.history.saved, None);
+  }
+
+  #[test]
+  fn states_follow_chips() {
+    let mut state = load();
+    state.states.get_mut("u2.'1q").unwrap().q = Voltage::High;
+    let index = state.source.borrow().iter().position(|x| matches!(x, ObjectEnum::Chip(_))).unwrap();
+    // the flip-flop is renumbered from u2 to u1
+    state.delete_object(index).unwrap();
+    assert_eq!(state.states["u1.'1q"].q, Voltage::High);
+    state.states.get_mut("u1.'1q").unwrap().q = Voltage::Low;
+    state.undo().unwrap();
+    assert_eq!(state.states["u2.'1q"].q, Voltage::High);
+    state.redo().unwrap();
+    assert_eq!(state.states["u1.'1q"].q, Voltage::Low);
+  }
+}
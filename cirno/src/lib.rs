@@ -1,25 +1,39 @@
-use crate::{command::CommandEnum, error::{CirnoError, try_to}, project::{Chip, Meta, Mode, Modes, Object, ObjectEnum, Pin, Value, Vector2, Voltage}, terminal::{EventResult, clear_all}};
+use crate::{command::CommandEnum, diagnostics::{Diagnostic, Diagnostics, Severity}, error::{CirnoError, try_to}, history::{Edit, Entry, History, SavedStates}, project::{Chip, Family, Header, Meta, SequentialState, Mode, Modes, Object, ObjectEnum, Pin, Span, Value, Vector2, Voltage, Wire}, terminal::{EventResult, clear_all}};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::time::Instant;
 use crossterm::{event::{Event, KeyCode, KeyEvent, KeyEventKind}, style::{Color, Colors}};
+use erc::Erc;
 use include_dir::{include_dir, Dir};
+use library::Origin;
+use modes::list::List;
+use modes::moving::Grab;
+use netlist::Netlist;
 use parser::parse;
+use simulator::Simulator;
 
 pub static STDLIB: Dir<'_> = include_dir!("../stdlib");
 
 pub mod bar;
 pub mod command;
 pub mod cursor;
+pub mod diagnostics;
+pub mod erc;
 pub mod error;
+pub mod ghost;
+pub mod history;
+pub mod library;
 pub mod logger;
 pub mod modes;
+pub mod netlist;
 pub mod parser;
 pub mod project;
 pub mod search;
+pub mod serializer;
+pub mod simulator;
 pub mod terminal;
 
 #[derive(Debug)]
@@ -28,79 +42,150 @@ pub struct CirnoState {
   pub rows: u16,
   pub project: Option<PathBuf>,
   pub mode: Modes,
+  pub last_mode: Modes,
   pub commands: HashMap<String, fn(Vec<String>) -> CommandEnum>,
   pub cursor: Vector2,
   pub char_under_cursor: (char, Color),
   pub objects: Rc<RefCell<Vec<ObjectEnum>>>,
+  pub source: Rc<RefCell<Vec<ObjectEnum>>>,
+  pub dirty: bool,
   pub meta: Meta,
   pub error: String,
   pub cic_data: HashMap<String, Vec<ObjectEnum>>,
+  // the header of each chip type in `cic_data`, inferred if its definition has none
+  pub cic_headers: HashMap<String, Header>,
   pub repeat_amount: u16,
   pub search_result: Rc<RefCell<Vec<ObjectEnum>>>,
+  pub diagnostics: Diagnostics,
+  pub ghost: Option<ObjectEnum>,
+  pub wire_color: Color,
+  pub grab: Option<Grab>,
+  pub history: History,
+  pub netlist: Netlist,
+  // what each sequential pin stores, by label, kept from one simulation to the next
+  pub states: HashMap<String, SequentialState>,
+  // the pins and wires involved in shorts and contention, which are highlighted
+  pub conflicts: Vec<ObjectEnum>,
+  pub erc: Erc,
+  // the list shown in list mode, if any
+  pub list: Option<List>,
 }
 
 impl CirnoState {
   /// Create a new CirnoState instance.
   pub fn new() -> Result<CirnoState, anyhow::Error> {
     let (columns, rows) = crossterm::terminal::size()?;
-    let cs = CirnoState {
+    Ok(CirnoState::with_size(columns, rows))
+  }
+  /// Create a new CirnoState instance for a terminal of the given size.
+  pub fn with_size(columns: u16, rows: u16) -> CirnoState {
+    CirnoState {
       columns,
       rows,
       project: None,
       mode: Modes::Empty,
+      last_mode: Modes::Empty,
       commands: command::get_all_commands(),
       cursor: Vector2::default(),
       char_under_cursor: (' ', crossterm::style::Color::White),
       objects: Rc::new(RefCell::new(vec![])),
+      source: Rc::new(RefCell::new(vec![])),
+      dirty: false,
       meta: Meta::default(),
       error: String::new(),
       cic_data: HashMap::new(),
+      cic_headers: HashMap::new(),
       repeat_amount: 0,
       search_result: Rc::new(RefCell::new(vec![])),
-    };
-    Ok(cs)
+      diagnostics: Diagnostics::default(),
+      ghost: None,
+      wire_color: Color::Red,
+      grab: None,
+      history: History::default(),
+      states: HashMap::new(),
+      conflicts: vec![],
+      netlist: Netlist::default(),
+      erc: Erc::default(),
+      list: None,
+    }
   }
   /// Get the current mode.
   pub fn get_mode(&mut self) -> Mode {
     match self.mode {
+      Modes::Chip => crate::modes::chip::get(),
       Modes::Console => crate::modes::console::get(),
       Modes::Empty => crate::modes::empty::get(),
+      Modes::Insert => crate::modes::insert::get(),
+      Modes::List => crate::modes::list::get(),
+      Modes::Move => crate::modes::moving::get(),
       Modes::Normal => crate::modes::normal::get(),
+      Modes::Wire => crate::modes::wire::get(),
     }
   }
   /// Set the current mode.
   pub fn set_mode(&mut self, mode: Modes) -> Result<(), anyhow::Error> {
+    self.last_mode = self.mode;
     self.mode = mode;
     try_to((self.get_mode().mode_set_cb)(self), self)?;
     Ok(())
   }
   /// Populate `cic_data` based on the chip types in `objects`.
   /// The top left pin of each chip added will have a position of (0, 0).
-  pub fn set_cic_data(&mut self) -> Result<(), anyhow::Error> {
-    let binding = self.objects.borrow();
-    let mut types: Vec<&str> = binding
+  /// A chip type that cannot be loaded is reported at the first chip with that type.
+  pub fn set_cic_data(&mut self, diagnostics: &mut Diagnostics) -> Result<(), anyhow::Error> {
+    let mut chips: Vec<(String, Span)> = self.objects
+      .borrow()
       .iter()
       .filter_map(|x| match x {
-        ObjectEnum::Chip(Chip { t, .. }) => Some(t.as_str()),
+        ObjectEnum::Chip(chip) => Some((chip.t.clone(), chip.span.clone())),
         _ => None,
       })
       .collect();
-    types.sort();
-    types.dedup();
-    for t in types {
-      let contents = stdlib(t)?;
-      let mut v = parse(&contents)?;
-      let len = v.len() / 2;
-      for (index, pin) in v.iter_mut().enumerate() {
-        let ObjectEnum::Pin(pin) = pin else { unreachable!(); };
-        pin.set_temp_region_position(index, len)?;
-        pin.set_region_size(self)?;
-      }
-      self.cic_data.insert(t.to_string(), v);
+    chips.sort_by(|a, b| a.0.cmp(&b.0));
+    chips.dedup_by(|a, b| a.0 == b.0);
+    for (t, span) in chips {
+      self.load_cic_data(&t, &span, diagnostics)?;
     }
     // logger::debug(format!("{:?}", self.cic_data));
     Ok(())
   }
+  /// Add a single chip type to `cic_data`, unless it is already there.
+  /// Returns false if the chip type could not be loaded, after adding to `diagnostics`
+  /// at `span`.
+  pub fn load_cic_data(&mut self, t: &str, span: &Span, diagnostics: &mut Diagnostics) -> Result<bool, anyhow::Error> {
+    if self.cic_data.contains_key(t) {
+      return Ok(true)
+    }
+    let (contents, origin) = match stdlib(t, self.project.as_deref()) {
+      Ok(found) => found,
+      Err(e) => {
+        diagnostics.error(span, e);
+        return Ok(false)
+      },
+    };
+    crate::logger::info(format!("{}: loaded from {}", t, origin));
+    let mut v = parse(&contents, &origin.file(t), diagnostics);
+    if !layout_pins(&mut v, &short_chip_type(t.to_string()), self, diagnostics)? {
+      return Ok(false)
+    }
+    let mut header = match v.first() {
+      Some(ObjectEnum::Header(header)) => header.clone(),
+      _ => Header::infer(t),
+    };
+    if header.family == Family::Unknown {
+      header.family = Family::from_chip_type(t);
+    }
+    v.retain(|x| matches!(x, ObjectEnum::Pin(_)));
+    // pins without numbers are numbered in the order they are listed
+    for (index, object) in v.iter_mut().enumerate() {
+      if let ObjectEnum::Pin(pin) = object {
+        pin.num = pin.num.or(Some(u16::try_from(index + 1).unwrap()));
+      }
+    }
+    self.cic_data.insert(t.to_string(), v);
+    self.cic_headers.insert(t.to_string(), header);
+    Ok(true)
+  }
   /// Set the `region.size` property of every object.
   pub fn set_region_sizes(&mut self) -> Result<(), anyhow::Error> {
     for object in self.objects.borrow_mut().iter_mut() {
@@ -140,34 +225,46 @@ impl CirnoState {
     }
     Ok(())
   }
-  /// Set the `voltage` property of every pin object connected to a net with a wire.
-  pub fn set_pin_voltages(&mut self) -> Result<(), anyhow::Error> {
-    let mut binding = self.objects.borrow_mut();
-    let (mut pins, mut wires, mut nets) = binding.iter_mut().fold((vec![], vec![], vec![]), |mut acc, x| {
-      match x {
-        ObjectEnum::Pin(pin) => acc.0.push(pin),
-        ObjectEnum::Wire(wire) => acc.1.push(wire),
-        ObjectEnum::Net(net) => acc.2.push(net),
-        _ => {},
-      };
-      acc
-    });
-    for pin in pins.iter_mut() {
-      let Some(wire) = wires.iter_mut().find(|x| x.is_connected_to(pin.region.position)) else { continue; };
-      let Some(net) = nets.iter_mut().find(|x| x.region.overlapping_vec2(wire.from) || x.region.overlapping_vec2(wire.to)) else { continue; };
-      pin.voltage = match net.t.as_str() {
-        "vcc" => Voltage::High,
-        "gnd" => Voltage::Low,
-        _ => unreachable!(),
+  /// Build `netlist` from `objects`, then set the `voltage` property of every pin object
+  /// on the same net as a rail.
+  pub fn set_pin_voltages(&mut self, diagnostics: &mut Diagnostics) -> Result<(), anyhow::Error> {
+    self.netlist = Netlist::build(&self.objects.borrow(), &self.meta);
+    self.conflicts.clear();
+    if !self.netlist.shorts.is_empty() {
+      let wires = self.wires();
+      let wires = self.netlist.path(&wires, &self.netlist.rail_places("vcc"), &self.netlist.rail_places("gnd"));
+      let span = wires.first().map(|x| x.span.clone()).unwrap_or_default();
+      diagnostics.error(&span, format!("the vcc and gnd rails are shorted{}", describe_wires(&wires)));
+      self.conflicts.extend(wires.into_iter().map(ObjectEnum::Wire));
+    }
+    for object in self.objects.borrow_mut().iter_mut() {
+      let ObjectEnum::Pin(pin) = object else { continue };
+      let rail = self.netlist.net_at(pin.region.position).and_then(|id| self.netlist.rail(id));
+      pin.voltage = match rail {
+        Some("vcc") => Voltage::High,
+        Some("gnd") => Voltage::Low,
+        _ => Voltage::Floating,
       };
-      if let Value::Nc = pin.value {
-        logger::warn(format!("pin at {:?} is {:?}, but was pulled {:?}", pin.region.position, pin.value, pin.voltage));
-      }
     }
     Ok(())
   }
-  /// Calculate the `voltage` property for every pin object with a calculable Value.
-  pub fn calculate_voltages_from_values(&mut self) -> Result<(), anyhow::Error> {
+  /// Return every wire in `objects`.
+  fn wires(&self) -> Vec<Wire> {
+    self.objects
+      .borrow()
+      .iter()
+      .filter_map(|x| match x {
+        ObjectEnum::Wire(wire) => Some(wire.clone()),
+        _ => None,
+      })
+      .collect()
+  }
+  /// Simulate the board until it settles, then set the `voltage` property of every pin
+  /// object from the net it is on.
+  /// Gates that never settle are reported to `diagnostics` as oscillating, and outputs that
+  /// conflict with each other or with a rail as errors.
+  pub fn simulate(&mut self, diagnostics: &mut Diagnostics) -> Result<(), anyhow::Error> {
+    let wires = self.wires();
     let mut binding = self.objects.borrow_mut();
     let mut pins: Vec<&mut Pin> = binding
       .iter_mut()
@@ -176,78 +273,95 @@ impl CirnoState {
         _ => None,
       })
       .collect();
-    let mut voltages: HashMap<String, Voltage> = HashMap::new();
-    for pin in pins.iter() {
-      if pin.label.is_empty() {
-        continue;
-      }
-      voltages.insert(pin.label.clone(), pin.voltage.clone());
+    let mut simulator = Simulator::new(&pins, &self.netlist);
+    let oscillating = simulator.settle(&mut pins, &mut self.states)?;
+    // forget what removed pins stored
+    self.states.retain(|label, _| pins.iter().any(|pin| pin.label == *label && matches!(pin.value, Value::Sequential(_))));
+    for (i, pin) in pins.iter_mut().enumerate() {
+      pin.voltage = simulator.voltage(i);
     }
-    for pin in pins.iter_mut() {
-      pin.voltage = pin.calculate_voltage_from_value(&voltages)?;
-      voltages.insert(pin.label.clone(), pin.voltage.clone());
+    for i in oscillating {
+      let pin = &pins[i];
+      let (x, y) = (pin.region.position.x, pin.region.position.y);
+      diagnostics.warn(&pin.span, format!("{} at ({}, {}) oscillates and never settles", pin.describe(), x, y));
+    }
+    for (net, high, low) in simulator.conflicts() {
+      let places = |gates: &Vec<usize>| gates.iter().map(|i| self.netlist.place(pins[*i].region.position)).collect::<Vec<Vector2>>();
+      let describe = |gates: &Vec<usize>| gates
+        .iter()
+        .map(|i| format!("{} at ({}, {})", pins[*i].describe(), pins[*i].region.position.x, pins[*i].region.position.y))
+        .collect::<Vec<String>>()
+        .join(", ");
+      let (message, path) = match self.netlist.rail(net) {
+        Some(t) => {
+          let (gates, level) = if high.is_empty() { (&low, "low") } else { (&high, "high") };
+          let verb = if gates.len() == 1 { "drives" } else { "drive" };
+          let message = format!("{} {} {} against the {} rail", describe(gates), verb, level, t);
+          (message, self.netlist.path(&wires, &places(gates), &self.netlist.rail_places(t)))
+        },
+        None => {
+          let message = format!("{} {} high while {} {} low", describe(&high), if high.len() == 1 { "drives" } else { "drive" }, describe(&low), if low.len() == 1 { "drives" } else { "drive" });
+          (message, self.netlist.path(&wires, &places(&high), &places(&low)))
+        },
+      };
+      let gates = [high, low].concat();
+      // point at the board rather than the chip definition, where there is a wire to point at
+      let span = path.first().map(|x| &x.span).unwrap_or(&pins[gates[0]].span);
+      diagnostics.error(span, format!("{}{}", message, describe_wires(&path)));
+      self.conflicts.extend(gates.iter().map(|i| ObjectEnum::Pin(pins[*i].clone())));
+      self.conflicts.extend(path.into_iter().map(ObjectEnum::Wire));
     }
     Ok(())
   }
   /// Replace the chips in `objects` with the corresponding pins from `cic_data`, updating the
   /// position of each.
+  /// Each chip is kept, followed by its pins, which refer back to it by its designator.
+  /// Chips without a designator are numbered in order, skipping the ones already taken.
   pub fn convert_chips(&mut self) -> Result<(), anyhow::Error> {
     let mut v: Vec<ObjectEnum> = vec![];
-    let mut chip_counts: HashMap<String, u32> = HashMap::new();
     let binding = self.objects.borrow();
+    let mut taken: Vec<String> = binding
+      .iter()
+      .filter_map(|x| match x {
+        ObjectEnum::Chip(chip) if !chip.name.is_empty() => Some(chip.name.clone()),
+        _ => None,
+      })
+      .collect();
+    let mut count = 0;
     for object in binding.iter().cloned() { // objects
-      if let ObjectEnum::Chip(chip) = object {
-        let short_chip_type = short_chip_type(chip.t.clone());
-        // update chip_counts based on chip type
-        if !chip_counts.contains_key(&chip.t) {
-          chip_counts.insert(chip.t.clone(), 0);
-        }
-        if let Some(c) = chip_counts.get_mut(&chip.t) {
-          *c += 1;
+      if let ObjectEnum::Chip(mut chip) = object {
+        if chip.name.is_empty() {
+          chip.name = loop {
+            count += 1;
+            let name = format!("u{}", count);
+            if !taken.contains(&name) {
+              break name
+            }
+          };
+          taken.push(chip.name.clone());
         }
-        let c = chip_counts.get(&chip.t).unwrap();
+        let family = self.cic_headers.get(&chip.t).unwrap().family;
         // for each pin
         for object in self.cic_data.get(&chip.t).unwrap().iter().cloned() {
           let ObjectEnum::Pin(mut pin) = object else { continue; };
           // update position
           pin.region.position.x += chip.region.position.x;
           pin.region.position.y += chip.region.position.y;
+          pin.family = family;
+          pin.chip = chip.name.clone();
           // update label
           if !pin.label.is_empty() {
-            pin.label = unique_label(pin.label, &short_chip_type, *c);
+            pin.label = unique_label(pin.label, &chip.name);
           }
           // update value
-          match pin.value {
-            Value::And(labels) => {
-              let v: Vec<String> = unique_label_vec(labels, &short_chip_type, *c);
-              pin.value = Value::And(v);
-            },
-            Value::Nand(labels) => {
-              let v: Vec<String> = unique_label_vec(labels, &short_chip_type, *c);
-              pin.value = Value::Nand(v);
-            },
-            Value::Nor(labels) => {
-              let v: Vec<String> = unique_label_vec(labels, &short_chip_type, *c);
-              pin.value = Value::Nor(v);
-            },
-            Value::Not(label) => {
-              let l = unique_label(label, &short_chip_type, *c);
-              pin.value = Value::Not(l);
-            },
-            Value::Or(labels) => {
-              let v: Vec<String> = unique_label_vec(labels, &short_chip_type, *c);
-              pin.value = Value::Or(v);
-            },
-            Value::Xor(a, b) => {
-              let la = unique_label(a, &short_chip_type, *c);
-              let lb = unique_label(b, &short_chip_type, *c);
-              pin.value = Value::Xor(la, lb);
-            },
-            _ => {},
-          }
+          pin.value = pin.value.map_labels(|label| unique_label(label, &chip.name));
+          pin.output = pin.output.map_labels(|label| unique_label(label, &chip.name));
           // push the updated pin
           v.push(ObjectEnum::Pin(pin));
         }
+        // the chip goes before its pins, so that the cursor reports a pin over its chip
+        let pins = self.cic_data.get(&chip.t).unwrap().len();
+        v.insert(v.len() - pins, ObjectEnum::Chip(chip));
       } else {
         v.push(object);
       }
@@ -303,10 +417,207 @@ impl CirnoState {
     for object in self.objects.borrow().iter() {
       object.render(Colors { foreground: None, background: None }, self)?;
     }
+    self.render_conflicts()?;
     cursor::render(self)?;
     cursor::report(self)?;
     Ok(())
   }
+  /// Draw the pins and wires involved in conflicts over the board.
+  pub fn render_conflicts(&self) -> Result<(), anyhow::Error> {
+    for object in self.conflicts.iter() {
+      object.render(Colors::new(Color::White, Color::Red), self)?;
+    }
+    Ok(())
+  }
+  /// Rebuild `objects` from the objects in `source`, then simulate them.
+  /// Each stage adds to `diagnostics`, and rebuilding stops after the first stage with
+  /// any errors.
+  pub fn rebuild(&mut self, diagnostics: &mut Diagnostics) -> Result<(), anyhow::Error> {
+    self.rebuild_objects(diagnostics)?;
+    if diagnostics.has_errors() {
+      return Ok(())
+    }
+    self.rebuild_simulation(diagnostics)
+  }
+  /// Rebuild `objects` from the objects in `source`, up to verifying them.
+  fn rebuild_objects(&mut self, diagnostics: &mut Diagnostics) -> Result<(), anyhow::Error> {
+    self.objects = Rc::new(RefCell::new(self.source.borrow().clone()));
+
+    self.set_cic_data(diagnostics)?;
+    if diagnostics.has_errors() {
+      return Ok(())
+    }
+    self.set_region_sizes()?;
+    self.set_wire_labels()?;
+
+    let now = Instant::now();
+    self.verify(diagnostics);
+    let elapsed = now.elapsed();
+    crate::logger::info(format!("verified in {:?}", elapsed));
+    if diagnostics.has_errors() {
+      return Ok(())
+    }
+    // keep the objects as they were before convert_chips, for writing and editing
+    self.source.replace(self.objects.borrow().clone());
+    Ok(())
+  }
+  /// Convert the chips in `objects` to pins, then find the nets and simulate them.
+  /// Errors found here are problems with the circuit rather than the objects, so the
+  /// board can still be shown and edited.
+  fn rebuild_simulation(&mut self, diagnostics: &mut Diagnostics) -> Result<(), anyhow::Error> {
+    self.convert_chips()?;
+    self.set_pin_voltages(diagnostics)?;
+    self.simulate(diagnostics)?;
+    Ok(())
+  }
+  /// Make a change to `source`, then rebuild `objects` and simulate them, putting back
+  /// what the sequential pins stored from `states` in between.
+  /// If the objects have any errors, or rebuilding fails, `source` and the sequential
+  /// states are put back as they were, and the errors are returned.
+  /// Errors in the circuit, such as shorts, are kept in `diagnostics` instead.
+  /// Pins in a chip definition have nothing derived from them, so they are changed directly.
+  fn edit(&mut self, edit: &Edit, states: &SavedStates) -> Result<(), anyhow::Error> {
+    if matches!(self.mode, Modes::Chip) {
+      edit.apply(&mut self.objects.borrow_mut());
+      edit.apply(&mut self.source.borrow_mut());
+      return Ok(())
+    }
+    let backup = (self.source.borrow().clone(), self.states.clone());
+    edit.apply(&mut self.source.borrow_mut());
+    let mut diagnostics = Diagnostics::default();
+    let mut result = self.rebuild_objects(&mut diagnostics);
+    let valid = result.is_ok() && !diagnostics.has_errors();
+    if valid {
+      // designators are given while converting, so the states are put back in between
+      result = self.convert_chips().and_then(|_| {
+        self.load_states(states);
+        self.set_pin_voltages(&mut diagnostics)?;
+        self.simulate(&mut diagnostics)
+      });
+    }
+    if !valid || result.is_err() {
+      self.source.replace(backup.0);
+      self.states = backup.1;
+      self.rebuild(&mut Diagnostics::default())?;
+      result?;
+      diagnostics.report()?;
+    }
+    diagnostics.log();
+    self.diagnostics = diagnostics;
+    Ok(())
+  }
+  /// Make a change to `source` and rebuild `objects`, recording it so that it can be undone.
+  pub fn apply_edit(&mut self, edit: Edit) -> Result<(), anyhow::Error> {
+    let states = self.save_states();
+    self.edit(&edit, &edit.shift(&states))?;
+    self.history.record(Entry { edit, states });
+    self.dirty = true;
+    Ok(())
+  }
+  /// Undo the last edit, returning its description, or None if there is nothing to undo.
+  /// What the sequential pins stored before the edit is put back before simulating again.
+  pub fn undo(&mut self) -> Result<Option<String>, anyhow::Error> {
+    let Some(entry) = self.history.undo.last().cloned() else { return Ok(None) };
+    let states = self.save_states();
+    self.edit(&entry.edit.inverse(), &entry.states)?;
+    self.history.undo.pop();
+    self.history.redo.push(Entry { edit: entry.edit.clone(), states });
+    self.dirty = !self.history.is_saved();
+    Ok(Some(entry.edit.describe()))
+  }
+  /// Redo the last edit that was undone, returning its description, or None if there is
+  /// nothing to redo.
+  pub fn redo(&mut self) -> Result<Option<String>, anyhow::Error> {
+    let Some(entry) = self.history.redo.last().cloned() else { return Ok(None) };
+    let states = self.save_states();
+    self.edit(&entry.edit, &entry.states)?;
+    self.history.redo.pop();
+    self.history.undo.push(Entry { edit: entry.edit.clone(), states });
+    self.dirty = !self.history.is_saved();
+    Ok(Some(entry.edit.describe()))
+  }
+  /// Return the index in `source` of each chip, by its designator.
+  fn chip_indices(&self) -> HashMap<String, usize> {
+    let source = self.source.borrow();
+    let indices = source
+      .iter()
+      .enumerate()
+      .filter(|(_, x)| matches!(x, ObjectEnum::Chip(_)))
+      .map(|(i, _)| i);
+    // converted chips keep the order they have in source
+    self.objects
+      .borrow()
+      .iter()
+      .filter_map(|x| match x {
+        ObjectEnum::Chip(chip) => Some(chip.name.clone()),
+        _ => None,
+      })
+      .zip(indices)
+      .collect()
+  }
+  /// Return what each sequential pin stores, by the index in `source` of its chip rather
+  /// than its designator, which changes when chips before it are added or removed.
+  pub fn save_states(&self) -> SavedStates {
+    let chips = self.chip_indices();
+    self.objects
+      .borrow()
+      .iter()
+      .filter_map(|x| match x {
+        ObjectEnum::Pin(pin) => self.states
+          .get(&pin.label)
+          .map(|state| ((chips.get(&pin.chip).copied(), pin.local_label().to_string()), state.clone())),
+        _ => None,
+      })
+      .collect()
+  }
+  /// Set what each sequential pin stores from `states`, once the chips have been converted.
+  fn load_states(&mut self, states: &SavedStates) {
+    let chips = self.chip_indices();
+    self.states = self.objects
+      .borrow()
+      .iter()
+      .filter_map(|x| match x {
+        ObjectEnum::Pin(pin) => states
+          .get(&(chips.get(&pin.chip).copied(), pin.local_label().to_string()))
+          .map(|state| (pin.label.clone(), state.clone())),
+        _ => None,
+      })
+      .collect();
+  }
+  /// Add an object to `source`, then rebuild `objects`.
+  pub fn insert_object(&mut self, object: ObjectEnum) -> Result<(), anyhow::Error> {
+    let index = self.source.borrow().len();
+    self.apply_edit(Edit::Insert(index, object))
+  }
+  /// Remove the object at `index` in `source`, then rebuild `objects`.
+  pub fn delete_object(&mut self, index: usize) -> Result<(), anyhow::Error> {
+    let object = self.source.borrow()[index].clone();
+    self.apply_edit(Edit::Delete(index, object))
+  }
+  /// Return the index in `source` of the object at a position.
+  /// As with `cursor::report`, a wire ending at the position is preferred over
+  /// whatever it is plugged into.
+  pub fn source_index_at(&self, position: Vector2) -> Option<usize> {
+    let source = self.source.borrow();
+    let wire = source.iter().position(|x| matches!(x, ObjectEnum::Wire(wire) if wire.is_connected_to(position)));
+    wire.or_else(|| source.iter().rposition(|x| x.get_region().is_some_and(|r| r.overlapping_vec2(position))))
+  }
+  /// Verify that an object could be added to `source` without overlapping any
+  /// other object, ignoring the object at index `skip`.
+  pub fn verify_placement(&self, object: &ObjectEnum, skip: Option<usize>, diagnostics: &mut Diagnostics) {
+    object.verify(self, diagnostics);
+    let Some(region) = object.get_region() else { return };
+    for (index, other_object) in self.source.borrow().iter().enumerate() {
+      if skip == Some(index) {
+        continue;
+      }
+      let Some(other_region) = other_object.get_region() else { continue };
+      if region.overlapping(other_region) {
+        let e = CirnoError::OverlappingRegion(other_object.get_span().clone());
+        diagnostics.error(object.get_span(), e);
+      }
+    }
+  }
   /// Return the meta object, or CirnoError::MetaObjectError if it cannot be found.
   pub fn find_meta(&mut self) -> Result<Meta, CirnoError> {
     self.objects
@@ -319,25 +630,62 @@ impl CirnoState {
       .ok_or(CirnoError::MetaObjectError)
   }
   /// Verify all objects.
-  pub fn verify(&mut self) -> Result<(), CirnoError> {
+  pub fn verify(&mut self, diagnostics: &mut Diagnostics) {
     for object in self.objects.borrow().iter() {
-      object.verify(self)?;
+      object.verify(self, diagnostics);
+    }
+    self.verify_overlap(diagnostics);
+    self.verify_designators(diagnostics);
+  }
+  /// Verify that no two chips were given the same designator.
+  pub fn verify_designators(&mut self, diagnostics: &mut Diagnostics) {
+    let objects = self.objects.borrow();
+    let chips: Vec<&Chip> = objects
+      .iter()
+      .filter_map(|x| match x {
+        ObjectEnum::Chip(chip) if !chip.name.is_empty() => Some(chip),
+        _ => None,
+      })
+      .collect();
+    for (index, chip) in chips.iter().enumerate() {
+      if let Some(other) = chips[..index].iter().find(|x| x.name == chip.name) {
+        diagnostics.error(&chip.span, CirnoError::DuplicateDesignator(chip.name.clone(), other.span.clone()));
+      }
     }
-    self.verify_overlap()?;
-    Ok(())
   }
   /// Verify that no objects overlap with each other.
-  pub fn verify_overlap(&mut self) -> Result<(), CirnoError> {
+  pub fn verify_overlap(&mut self, diagnostics: &mut Diagnostics) {
     for (index, object) in self.objects.borrow().iter().enumerate() {
       let Some(region) = object.get_region() else { continue };
-      for (other_index, other_object) in self.objects.borrow().iter().enumerate().filter(|x| x.0 > index) {
+      for other_object in self.objects.borrow().iter().skip(index + 1) {
         let Some(other_region) = other_object.get_region() else { continue };
+        // the pins of a converted chip sit inside it, once it has been given a designator
+        if let (ObjectEnum::Chip(chip), ObjectEnum::Pin(pin)) = (object, other_object) {
+          if !chip.name.is_empty() && pin.chip == chip.name {
+            continue
+          }
+        }
         if region.overlapping(other_region) {
-          return Err(CirnoError::OverlappingRegion(index, other_index))
+          let e = CirnoError::OverlappingRegion(object.get_span().clone());
+          diagnostics.error(other_object.get_span(), e);
         }
       }
     }
-    Ok(())
+  }
+  /// Write the project to the given path, or to `project` if no path is given.
+  /// Only writing to `project` marks the state as clean.
+  pub fn write(&mut self, path: Option<PathBuf>) -> Result<PathBuf, anyhow::Error> {
+    let Some(project) = self.project.clone() else {
+      return Err(CirnoError::NoProjectOpen.into())
+    };
+    let path = path.unwrap_or(project.clone());
+    fs::write(&path, serializer::serialize(&self.source.borrow()))?;
+    if path == project {
+      self.dirty = false;
+      self.history.mark_saved();
+    }
+    crate::logger::info(format!("wrote {}", path.display()));
+    Ok(path)
   }
   /// Verify that the size of the terminal is large enough to fit the bounds.
   pub fn verify_size(&mut self) -> Result<(), CirnoError> {
@@ -359,36 +707,34 @@ pub fn open(path: PathBuf, state: &mut CirnoState) -> Result<(), anyhow::Error>
   if extension.is_none() {
     return Err(CirnoError::InvalidFile(filename.to_string()).into());
   }
-  let contents = match extension.unwrap().to_str().unwrap() { // converts from Option<&OsStr> to &str
-    "cip" => fs::read_to_string(&path)?,
-    "cic" => return Err(CirnoError::OpenCicNotImplemented.into()),
+  let extension = extension.unwrap().to_str().unwrap(); // converts from Option<&OsStr> to &str
+  let contents = match extension {
+    "cip" | "cic" => fs::read_to_string(&path)?,
     x => return Err(CirnoError::InvalidFiletype(x.to_string()).into()),
   };
-  if contents.eq("") {
+  if contents.is_empty() {
     return Ok(())
   }
+  if extension == "cic" {
+    return open_cic(path, &contents, state);
+  }
 
   // operate on a new instance of state
   let mut ns = CirnoState::new()?;
+  let mut diagnostics = Diagnostics::default();
 
   ns.mode = Modes::Normal; // skip the mode set callback
-  ns.project = Some(path);
-  ns.objects = Rc::new(RefCell::new(parser::parse(&contents)?));
-  ns.meta = ns.find_meta()?;
+  ns.project = Some(path.clone());
+  let result = load(&mut ns, &contents, filename, &mut diagnostics);
+  // every diagnostic is reported together, even if loading stopped early
+  diagnostics.report()?;
+  result?;
   ns.verify_size()?;
-
-  ns.set_cic_data()?;
-  ns.set_region_sizes()?;
-  ns.set_wire_labels()?;
-
-  let now = Instant::now();
-  ns.verify()?;
-  let elapsed = now.elapsed();
-  crate::logger::info(format!("verified in {:?}", elapsed));
-
-  ns.convert_chips()?;
-  ns.set_pin_voltages()?;
-  ns.calculate_voltages_from_values()?;
+  // problems with the circuit are shown on the board rather than stopping it from opening
+  let mut circuit = Diagnostics::default();
+  ns.rebuild_simulation(&mut circuit)?;
+  circuit.log();
+  diagnostics.items.append(&mut circuit.items);
 
   clear_all()?;
 
@@ -398,26 +744,225 @@ pub fn open(path: PathBuf, state: &mut CirnoState) -> Result<(), anyhow::Error>
   // bar::message(format!("{:?}", elapsed), &state)?;
   crate::logger::info(format!("rendered in {:?}", elapsed));
 
+  let errors = diagnostics.count(Severity::Error);
+  let warnings = diagnostics.count(Severity::Warning);
+  if errors > 0 {
+    bar::message(CirnoError::Diagnostics(errors, warnings).to_string(), &ns)?;
+  } else if warnings > 0 {
+    bar::message(format!("{} warnings (press C to view)", warnings), &ns)?;
+  }
+
+  // reassign
+  ns.diagnostics = diagnostics;
+  *state = ns;
+
+  Ok(())
+}
+
+/// Load the contents of a cirno project into a new instance of state, without simulating it.
+/// Each stage adds to `diagnostics`, and loading stops after the first stage with
+/// any errors.
+fn load(ns: &mut CirnoState, contents: &str, filename: &str, diagnostics: &mut Diagnostics) -> Result<(), anyhow::Error> {
+  ns.source = Rc::new(RefCell::new(parser::parse(contents, filename, diagnostics)));
+  if diagnostics.has_errors() {
+    return Ok(())
+  }
+  ns.objects = Rc::new(RefCell::new(ns.source.borrow().clone()));
+  ns.meta = ns.find_meta()?;
+  ns.rebuild_objects(diagnostics)
+}
+
+/// Load a cirno project without a terminal, then check it against the electrical rules.
+/// Returns every problem found while loading and simulating it, followed by the results
+/// of the check, which is skipped if the project could not be loaded.
+pub fn check(path: PathBuf) -> Result<Vec<Diagnostic>, anyhow::Error> {
+  let filename = path.to_str().unwrap();
+  match path.extension().and_then(|x| x.to_str()) {
+    Some("cip") => {},
+    Some(x) => return Err(CirnoError::InvalidFiletype(x.to_string()).into()),
+    None => return Err(CirnoError::InvalidFile(filename.to_string()).into()),
+  }
+  let contents = fs::read_to_string(&path)?;
+  let mut ns = CirnoState::with_size(0, 0);
+  let mut diagnostics = Diagnostics::default();
+  ns.project = Some(path.clone());
+  load(&mut ns, &contents, filename, &mut diagnostics)?;
+  if !diagnostics.has_errors() {
+    ns.rebuild_simulation(&mut diagnostics)?;
+    diagnostics.items.extend(erc::check(&ns));
+  }
+  Ok(diagnostics.items)
+}
+
+/// Open a chip definition in the chip editor.
+fn open_cic(path: PathBuf, contents: &str, state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  let mut ns = CirnoState::new()?;
+  let mut diagnostics = Diagnostics::default();
+
+  let mut pins = parser::parse(contents, path.to_str().unwrap(), &mut diagnostics);
+  if !diagnostics.has_errors() {
+    let name = path.file_stem().and_then(|x| x.to_str()).unwrap_or_default();
+    layout_pins(&mut pins, name, &ns, &mut diagnostics)?;
+  }
+  diagnostics.report()?;
+  ns.project = Some(path);
+  let len = pins.iter().filter(|x| matches!(x, ObjectEnum::Pin(_))).count();
+  ns.meta.bounds = Vector2 { x: u16::try_from(len / 2).unwrap(), y: 3 };
+  ns.cursor = Vector2 { x: 0, y: 2 }; // pin 1
+  ns.source = Rc::new(RefCell::new(pins.clone()));
+  ns.objects = Rc::new(RefCell::new(pins));
+
   // reassign
   *state = ns;
+  state.set_mode(Modes::Chip)?;
 
   Ok(())
 }
 
-pub fn stdlib(filename: &str) -> Result<String, anyhow::Error> {
-  // let out_dir = std::env::var_os("OUT_DIR").unwrap();
-  // let path = Path::new(&out_dir).join(format!("stdlib/{}.cic", filename));
+/// Validate a chip definition, then lay out its pins as if the top left pin were at (0, 0).
+/// `name` is the chip that the file defines, which its header must name.
+/// Pins are laid out by their numbers if they have them, or in the order they are listed.
+/// Returns false if the definition is invalid, after adding to `diagnostics`.
+fn layout_pins(v: &mut [ObjectEnum], name: &str, state: &CirnoState, diagnostics: &mut Diagnostics) -> Result<bool, anyhow::Error> {
+  let start = diagnostics.count(Severity::Error);
+  for (index, object) in v.iter().enumerate() {
+    match object {
+      ObjectEnum::Header(header) if index == 0 => {
+        if header.name != name {
+          diagnostics.error(&header.span, CirnoError::HeaderNameMismatch(header.name.clone(), name.to_string()));
+        }
+      },
+      ObjectEnum::Header(header) => diagnostics.error(&header.span, CirnoError::MisplacedHeader),
+      ObjectEnum::Pin(pin) => {
+        if pin.explicit_direction.is_some_and(|x| x != pin.inferred_direction()) {
+          diagnostics.error(&pin.span, CirnoError::DirectionMismatch(pin.direction().keyword().to_string()));
+        }
+      },
+      object => diagnostics.error(object.get_span(), CirnoError::InvalidObjectTypeForChip),
+    }
+  }
+  let pins: Vec<&Pin> = v
+    .iter()
+    .filter_map(|x| match x {
+      ObjectEnum::Pin(pin) => Some(pin),
+      _ => None,
+    })
+    .collect();
+  let len = pins.len();
+  if len == 0 {
+    diagnostics.error(&v.last().map(|x| x.get_span().clone()).unwrap_or_default(), CirnoError::NoPins);
+  } else if !len.is_multiple_of(2) {
+    diagnostics.error(v.last().unwrap().get_span(), CirnoError::OddPinCount(len));
+  }
+  if let Some(ObjectEnum::Header(Header { package: Some(package), span, .. })) = v.first() {
+    if package.pins() != len {
+      diagnostics.error(span, CirnoError::PackageMismatch(*package, len));
+    }
+  }
+  // pin numbers are all or nothing, and each is used once
+  let mut used: Vec<u16> = vec![];
+  for pin in pins.iter() {
+    match pin.num {
+      None if pins.iter().any(|x| x.num.is_some()) => diagnostics.error(&pin.span, CirnoError::MissingPinNumber),
+      Some(num) if num == 0 || usize::from(num) > len => diagnostics.error(&pin.span, CirnoError::PinNumberOutOfRange(num, len)),
+      Some(num) if used.contains(&num) => diagnostics.error(&pin.span, CirnoError::DuplicatePinNumber(num)),
+      Some(num) => used.push(num),
+      None => {},
+    }
+  }
+  if diagnostics.count(Severity::Error) > start {
+    return Ok(false)
+  }
+  let width = len / 2;
+  let mut index = 0;
+  for object in v.iter_mut() {
+    let ObjectEnum::Pin(pin) = object else { continue };
+    pin.set_temp_region_position(pin.num.map(|x| usize::from(x) - 1).unwrap_or(index), width)?;
+    pin.set_region_size(state)?;
+    index += 1;
+  }
+  Ok(true)
+}
+
+/// Find the chip definition for a chip type, returning its contents and where it
+/// came from.
+/// Each directory in the library search path is tried in order, falling back to
+/// the embedded stdlib.
+pub fn stdlib(filename: &str, project: Option<&Path>) -> Result<(String, Origin), anyhow::Error> {
+  let search_path = library::search_path(project);
+  for dir in search_path.iter() {
+    let path = dir.join(format!("{}.cic", filename));
+    if path.is_file() {
+      let contents = fs::read_to_string(path)?;
+      return Ok((contents, Origin::Directory(dir.to_path_buf())))
+    }
+  }
   let file = STDLIB.get_file(format!("{}.cic", filename));
-  // if !path.exists() {
   if file.is_none() {
-    return Err(CirnoError::NotFoundInStdlib(filename.to_string()).into())
+    let searched: Vec<String> = search_path
+      .iter()
+      .map(|p| p.display().to_string())
+      .chain([Origin::Embedded.to_string()])
+      .collect();
+    return Err(CirnoError::NotFoundInStdlib(filename.to_string(), searched.join(", ")).into())
   }
-  // let contents = fs::read_to_string(path)?;
   let contents = file.unwrap().contents_utf8().unwrap().to_string();
-  Ok(contents)
+  Ok((contents, Origin::Embedded))
+}
+
+/// Count the chip definitions in each library on the search path that exists,
+/// followed by the embedded stdlib.
+pub fn count_stdlib(project: Option<&Path>) -> Vec<(Origin, u16)> {
+  let mut counts: Vec<(Origin, u16)> = library::search_path(project)
+    .into_iter()
+    .filter(|dir| dir.is_dir())
+    .map(|dir| {
+      let files = u16::try_from(library::chip_types_in(&dir).len()).unwrap_or(u16::MAX);
+      (Origin::Directory(dir), files)
+    })
+    .collect();
+  counts.push((Origin::Embedded, count_embedded_stdlib()));
+  counts
+}
+
+/// Describe the wires involved in a conflict, e.g. ` through the wire from (1, 2) to (3, 4)`.
+fn describe_wires(wires: &[Wire]) -> String {
+  let wires: Vec<String> = wires
+    .iter()
+    .map(|x| format!("from ({}, {}) to ({}, {})", x.from.x, x.from.y, x.to.x, x.to.y))
+    .collect();
+  match wires.len() {
+    0 => String::new(),
+    1 => format!(" through the wire {}", wires[0]),
+    _ => format!(" through the wires {}", wires.join(", ")),
+  }
+}
+
+/// Return every chip type that can be loaded, from the libraries on the search path
+/// and the embedded stdlib, sorted and without duplicates.
+pub fn chip_types(project: Option<&Path>) -> Vec<String> {
+  let mut types: Vec<String> = library::search_path(project)
+    .iter()
+    .flat_map(|dir| library::chip_types_in(dir))
+    .collect();
+  let mut dirs = vec![&STDLIB];
+  while let Some(dir) = dirs.pop() {
+    for entry in dir.entries() {
+      match entry {
+        include_dir::DirEntry::Dir(sub_dir) => dirs.push(sub_dir),
+        include_dir::DirEntry::File(file) => {
+          let path = file.path().with_extension("");
+          types.push(path.to_string_lossy().replace('\\', "/"));
+        },
+      }
+    }
+  }
+  types.sort();
+  types.dedup();
+  types
 }
 
-pub fn count_stdlib() -> u16 {
+fn count_embedded_stdlib() -> u16 {
   let mut files = 0;
   let mut dirs = vec![];
   // every DirEntry at the top level of STDLIB matches the Dir variant
@@ -447,18 +992,83 @@ pub fn color_to_string(color: Color) -> String {
   }
 }
 
+/// Return the wire color for a letter, as used by searches and the color picker.
+pub fn color_from_char(c: char) -> Option<Color> {
+  match c {
+    'r' => Some(Color::Red),
+    'g' => Some(Color::Green),
+    'y' => Some(Color::Yellow),
+    'b' => Some(Color::Blue),
+    'm' => Some(Color::Magenta),
+    'c' => Some(Color::Cyan),
+    _ => None,
+  }
+}
+
 pub fn short_chip_type(t: String) -> String {
   t.split('/').collect::<Vec<&str>>().last().unwrap().to_string()
 }
 
-pub fn unique_label(original: String, t: &str, count: u32) -> String {
-  format!("{}_{}_{}", original, t, count)
+/// Return the label of a pin on the chip with the given designator, which is unique
+/// across the board, e.g. `u3.'2a`.
+pub fn unique_label(original: String, designator: &str) -> String {
+  format!("{}.{}", designator, original)
 }
 
-pub fn unique_label_vec(originals: Vec<String>, t: &str, count: u32) -> Vec<String> {
+pub fn unique_label_vec(originals: Vec<String>, designator: &str) -> Vec<String> {
   let mut v: Vec<String> = vec![];
   for original in originals {
-    v.push(unique_label(original, t, count));
+    v.push(unique_label(original, designator));
   }
   v
 }
+
+#[cfg(test)]
+mod tests {
+  use crate::{CirnoState, diagnostics::Diagnostics, project::{ObjectEnum, Value}};
+
+  #[test]
+  fn convert_chips() {
+    let contents = "\
+: meta bounds 40 15
+: chip type ls0xx/sn74ls04 pos 0 2
+: chip name u2 type ls0xx/sn74ls04 pos 8 2
+: chip type ls0xx/sn74ls04 pos 16 2
+: chip type ls0xx/sn74ls04 pos 24 2
+: chip name u3 type ls0xx/sn74ls04 pos 32 2
+";
+    let mut state = CirnoState::with_size(0, 0);
+    let mut diagnostics = Diagnostics::default();
+    crate::load(&mut state, contents, "test.cip", &mut diagnostics).unwrap();
+    state.convert_chips().unwrap();
+    let objects = state.objects.borrow();
+    let chips: Vec<(usize, String)> = objects
+      .iter()
+      .enumerate()
+      .filter_map(|(i, x)| match x {
+        ObjectEnum::Chip(chip) => Some((i, chip.name.clone())),
+        _ => None,
+      })
+      .collect();
+    // unnamed chips are numbered in order, skipping names taken anywhere on the board
+    let names: Vec<&str> = chips.iter().map(|(_, name)| name.as_str()).collect();
+    assert_eq!(names, ["u1", "u2", "u4", "u5", "u3"]);
+    for (i, name) in chips {
+      // each chip comes just before its 14 pins
+      for object in &objects[i + 1..i + 15] {
+        let ObjectEnum::Pin(pin) = object else { panic!("{:?}", object) };
+        assert_eq!(&pin.chip, &name);
+        assert!(pin.label.is_empty() || pin.label.starts_with(&format!("{}.'", name)), "{}", pin.label);
+      }
+    }
+    // labels in values are made unique as well
+    let pin = objects
+      .iter()
+      .find_map(|x| match x {
+        ObjectEnum::Pin(pin) if pin.label == "u4.'1y" => Some(pin),
+        _ => None,
+      })
+      .unwrap();
+    assert!(matches!(&pin.value, Value::Not(label) if label == "u4.'1a"));
+  }
+}
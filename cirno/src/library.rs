@@ -0,0 +1,126 @@
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where a chip definition was loaded from.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Origin {
+  Directory(PathBuf),
+  Embedded,
+}
+
+impl Origin {
+  /// Return the name of the file that a chip type would be loaded from.
+  pub fn file(&self, t: &str) -> String {
+    match self {
+      Origin::Directory(path) => path.join(format!("{}.cic", t)).display().to_string(),
+      Origin::Embedded => format!("stdlib/{}.cic", t),
+    }
+  }
+}
+
+impl Display for Origin {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+    match self {
+      Origin::Directory(path) => write!(f, "{}", path.display()),
+      Origin::Embedded => f.write_str("embedded stdlib"),
+    }
+  }
+}
+
+/// Return the directories to search for chip definitions, in order:
+/// the `lib/` folder next to the project, each path in `CIRNO_PATH`, and the
+/// user config directory.
+/// The embedded stdlib is searched after all of these.
+pub fn search_path(project: Option<&Path>) -> Vec<PathBuf> {
+  let mut v: Vec<PathBuf> = vec![];
+  if let Some(dir) = project.and_then(|p| p.parent()) {
+    v.push(dir.join("lib"));
+  }
+  if let Some(paths) = env::var_os("CIRNO_PATH") {
+    v.extend(env::split_paths(&paths).filter(|p| !p.as_os_str().is_empty()));
+  }
+  if let Some(dir) = config_dir() {
+    v.push(dir.join("cirno").join("lib"));
+  }
+  v
+}
+
+/// Return the user config directory, if it can be found.
+fn config_dir() -> Option<PathBuf> {
+  if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+    return Some(PathBuf::from(dir))
+  }
+  if cfg!(windows) {
+    return env::var_os("APPDATA").map(PathBuf::from)
+  }
+  env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"))
+}
+
+/// Return the path of every .cic file under `dir`, relative to `dir` and
+/// without the extension, e.g. `ls0xx/sn74ls08`.
+pub fn chip_types_in(dir: &Path) -> Vec<String> {
+  let mut types: Vec<String> = vec![];
+  let mut dirs = vec![dir.to_path_buf()];
+  while let Some(d) = dirs.pop() {
+    let Ok(entries) = fs::read_dir(&d) else { continue };
+    for entry in entries.flatten() {
+      let path = entry.path();
+      if path.is_dir() {
+        dirs.push(path);
+      } else if path.extension().is_some_and(|x| x == "cic") {
+        let relative = path.strip_prefix(dir).unwrap().with_extension("");
+        let parts: Vec<String> = relative.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+        types.push(parts.join("/"));
+      }
+    }
+  }
+  types.sort();
+  types
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{env, fs, path::Path};
+  use super::Origin;
+
+  fn write(path: &Path, contents: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+  }
+
+  #[test]
+  fn lookup_order() {
+    let root = env::temp_dir().join(format!("cirno-library-{}", std::process::id()));
+    let project = root.join("project");
+    let cirno_path = root.join("path");
+    let config = root.join("config");
+    // the chip type is only used here, so that other tests are not affected
+    let files = [
+      project.join("lib/test/lookup.cic"),
+      cirno_path.join("test/lookup.cic"),
+      config.join("cirno/lib/test/lookup.cic"),
+    ];
+    for (i, file) in files.iter().enumerate() {
+      write(file, &i.to_string());
+    }
+    env::set_var("CIRNO_PATH", &cirno_path);
+    env::set_var("XDG_CONFIG_HOME", &config);
+    let board = project.join("board.cip");
+    let expected = [project.join("lib"), cirno_path.clone(), config.join("cirno/lib")];
+    assert_eq!(super::search_path(Some(&board)), expected);
+    for (i, file) in files.iter().enumerate() {
+      let (contents, origin) = crate::stdlib("test/lookup", Some(&board)).unwrap();
+      assert_eq!(contents, i.to_string());
+      assert_eq!(origin, Origin::Directory(expected[i].clone()));
+      fs::remove_file(file).unwrap();
+    }
+    assert!(crate::stdlib("test/lookup", Some(&board)).is_err());
+    let (_, origin) = crate::stdlib("ls0xx/sn74ls04", Some(&board)).unwrap();
+    assert_eq!(origin, Origin::Embedded);
+    env::remove_var("CIRNO_PATH");
+    env::remove_var("XDG_CONFIG_HOME");
+    fs::remove_dir_all(&root).unwrap();
+  }
+}
@@ -1,6 +1,6 @@
 // need to use "cirno" in this file, not "crate"
 
-use cirno::{CirnoState, count_stdlib, open, command::{Command, Splash}, error::try_to, logger};
+use cirno::{CirnoState, check, count_stdlib, open, command::{Command, Splash}, diagnostics::Severity, erc, error::try_to, logger};
 use std::time::Duration;
 use clap::Parser;
 
@@ -8,6 +8,9 @@ use clap::Parser;
 #[derive(Parser)]
 struct Cli {
   filename: Option<std::path::PathBuf>,
+  /// Check the project against the electrical rules and print the results, without opening it
+  #[arg(long, requires = "filename")]
+  erc: bool,
 }
 
 fn main() -> Result<(), anyhow::Error> {
@@ -18,10 +21,26 @@ fn main() -> Result<(), anyhow::Error> {
   }));
 
   let args = Cli::parse();
+  if args.erc {
+    let results = check(args.filename.unwrap())?;
+    if results.is_empty() {
+      println!("no problems found");
+    }
+    for line in erc::summary(&results) {
+      println!("{}", line);
+    }
+    // fail if there are errors, so that the check can be scripted
+    if results.iter().any(|x| x.severity == Severity::Error) {
+      std::process::exit(1);
+    }
+    return Ok(())
+  }
   let mut state = CirnoState::new()?;
 
   // logger::info(format!("cirno"));
-  logger::info(format!("stdlib: loaded {} files", count_stdlib()));
+  for (origin, files) in count_stdlib(args.filename.as_deref()) {
+    logger::info(format!("stdlib: loaded {} files from {}", files, origin));
+  }
   logger::info(format!("editor: loaded {} commands", state.commands.keys().count()));
 
   cirno::terminal::enter()?;
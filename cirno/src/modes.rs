@@ -1,3 +1,8 @@
+pub mod chip;
 pub mod console;
 pub mod empty;
+pub mod insert;
+pub mod list;
+pub mod moving;
 pub mod normal;
+pub mod wire;
@@ -0,0 +1,330 @@
+use crate::{bar, command, diagnostics::Diagnostics, error::CirnoError, history::Edit, parser, project::{Header, Mode, Modes, Object, ObjectEnum, Pin, Value, Vector2, Voltage}, terminal::{EventResult, clear_all, move_to, read_line}, CirnoState};
+use std::collections::HashMap;
+use std::io::stdout;
+use crossterm::{execute, event::{KeyCode, KeyModifiers}, style::{Color, Colors}};
+
+/// The number of columns taken up by each pin in the pinout.
+const CELL: u16 = 6;
+/// The most inputs that a truth table is drawn for, at 1024 rows.
+const MAX_TABLE_INPUTS: usize = 10;
+
+pub fn get() -> Mode {
+  Mode {
+    mode_set_cb: on_mode_set,
+    key_event_cb,
+    resize_event_cb: handle_resize_event,
+    key_commands: HashMap::from([
+      ('h', on_key_h as _),
+      ('j', on_key_j as _),
+      ('k', on_key_k as _),
+      ('l', on_key_l as _),
+      ('e', on_key_e as _),
+      ('u', on_key_u as _),
+      ('C', on_key_cap_c as _),
+      (':', on_key_colon as _),
+    ]),
+  }
+}
+
+fn on_mode_set(state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  render(state)?;
+  Ok(())
+}
+
+fn key_event_cb(code: KeyCode, modifiers: KeyModifiers, state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  if let KeyCode::Char(c) = code {
+    if c == 'c' && modifiers.contains(KeyModifiers::CONTROL) {
+      bar::message("type  :q  and press <Enter> to exit cirno".to_string(), state)?;
+    }
+    if c == 'r' && modifiers.contains(KeyModifiers::CONTROL) {
+      let message = match state.redo()? {
+        Some(description) => format!("redo: {}", description),
+        None => "already at newest change".to_string(),
+      };
+      render(state)?;
+      bar::message(message, state)?;
+      return Ok(EventResult::Ok)
+    }
+  }
+  Ok(EventResult::Drop)
+}
+
+fn handle_resize_event(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  render(state)?;
+  Ok(EventResult::Ok)
+}
+
+/// Return the index of the pin at a position, which is one less than its pin number.
+/// Pins are numbered counterclockwise from the bottom left, as laid out by
+/// `Pin::set_temp_region_position`.
+fn index_at(position: Vector2, state: &CirnoState) -> usize {
+  let width = usize::from(state.meta.bounds.x);
+  let x = usize::from(position.x);
+  match position.y {
+    0 => width * 2 - x - 1,
+    _ => x,
+  }
+}
+
+/// Return the index in `objects` of the pin at the cursor.
+/// This differs from the pin number when pins are numbered out of order, or when
+/// there is a header.
+fn selected_object(state: &CirnoState) -> Option<usize> {
+  state.objects
+    .borrow()
+    .iter()
+    .position(|x| matches!(x, ObjectEnum::Pin(pin) if pin.region.position == state.cursor))
+}
+
+/// Return the pin at the cursor.
+fn selected_pin(state: &CirnoState) -> Option<Pin> {
+  match state.objects.borrow().get(selected_object(state)?) {
+    Some(ObjectEnum::Pin(pin)) => Some(pin.clone()),
+    _ => None,
+  }
+}
+
+/// Return the header of the chip being edited, if it has one.
+fn header(state: &CirnoState) -> Option<Header> {
+  match state.objects.borrow().first() {
+    Some(ObjectEnum::Header(header)) => Some(header.clone()),
+    _ => None,
+  }
+}
+
+/// Return the pins whose values should be shown in the truth table for a pin.
+/// This is the pin itself if its value can be calculated, or otherwise every pin
+/// whose value depends on it.
+/// Sequential pins depend on what they store, so they have no truth table.
+fn table_outputs(pin: &Pin, state: &CirnoState) -> Vec<Pin> {
+  if matches!(pin.value, Value::Sequential(_)) {
+    return vec![]
+  }
+  if !pin.value.inputs().is_empty() {
+    return vec![pin.clone()]
+  }
+  if pin.label.is_empty() {
+    return vec![]
+  }
+  state.objects
+    .borrow()
+    .iter()
+    .filter_map(|x| match x {
+      ObjectEnum::Pin(p) if p.value.inputs().contains(&pin.label) && !matches!(p.value, Value::Sequential(_)) => Some(p.clone()),
+      _ => None,
+    })
+    .collect()
+}
+
+/// Render the pinout of the chip being edited, along with information about the
+/// selected pin and a truth table.
+pub fn render(state: &CirnoState) -> Result<(), anyhow::Error> {
+  clear_all()?;
+  let width = state.meta.bounds.x;
+  let left = (state.columns / 2).saturating_sub(width * CELL / 2 + 1);
+  let top = 1;
+  // header
+  if let Some(header) = header(state) {
+    let mut line = header.name.clone();
+    let details: Vec<String> = [header.package.map(|x| x.to_string()), header.family.keyword().map(|x| x.to_string())]
+      .into_iter()
+      .flatten()
+      .collect();
+    if !details.is_empty() {
+      line.push_str(&format!(" ({})", details.join(", ")));
+    }
+    if !header.description.is_empty() {
+      line.push_str(&format!(": {}", header.description));
+    }
+    move_to(left, 0)?;
+    execute!(stdout(), crossterm::style::Print(line))?;
+  }
+  // pins
+  for object in state.objects.borrow().iter() {
+    let ObjectEnum::Pin(pin) = object else { continue };
+    let index = index_at(pin.region.position, state);
+    let x = left + 1 + pin.region.position.x * CELL;
+    let (number_y, label_y) = match pin.region.position.y {
+      0 => (top, top + 1),
+      _ => (top + 6, top + 5),
+    };
+    let (report, color) = pin.report(state)?;
+    move_to(x, number_y)?;
+    execute!(stdout(), crossterm::style::SetForegroundColor(Color::DarkGrey))?;
+    execute!(stdout(), crossterm::style::Print(format!("{:>w$}", index + 1, w = usize::from(CELL - 1))))?;
+    move_to(x, label_y)?;
+    if pin.region.position == state.cursor {
+      execute!(stdout(), crossterm::style::SetColors(Colors::new(Color::Black, Color::Yellow)))?;
+    } else {
+      execute!(stdout(), crossterm::style::SetForegroundColor(color))?;
+    }
+    execute!(stdout(), crossterm::style::Print(format!("{:>w$}", report, w = usize::from(CELL - 1))))?;
+    execute!(stdout(), crossterm::style::ResetColor)?;
+  }
+  // body
+  let body_width = usize::from(width * CELL + 1);
+  execute!(stdout(), crossterm::style::SetForegroundColor(Color::DarkGrey))?;
+  move_to(left, top + 2)?;
+  execute!(stdout(), crossterm::style::Print("~".repeat(body_width + 1)))?;
+  move_to(left, top + 3)?;
+  execute!(stdout(), crossterm::style::Print(format!("({}~", " ".repeat(body_width - 1))))?;
+  move_to(left, top + 4)?;
+  execute!(stdout(), crossterm::style::Print("~".repeat(body_width + 1)))?;
+  execute!(stdout(), crossterm::style::ResetColor)?;
+  // selected pin
+  let Some(pin) = selected_pin(state) else { return Ok(()) };
+  move_to(left, top + 8)?;
+  let number = index_at(pin.region.position, state) + 1;
+  execute!(stdout(), crossterm::style::Print(format!("pin {}: {} {} {:?}", number, pin.label, pin.direction().keyword(), pin.value)))?;
+  if let Some(comment) = &pin.comments.trailing {
+    execute!(stdout(), crossterm::style::SetForegroundColor(Color::DarkGrey))?;
+    execute!(stdout(), crossterm::style::Print(format!(" {}", comment)))?;
+    execute!(stdout(), crossterm::style::ResetColor)?;
+  }
+  render_truth_table(&pin, left, top + 10, state)?;
+  Ok(())
+}
+
+/// Render a truth table for the given pin, starting at (x, y).
+fn render_truth_table(pin: &Pin, x: u16, y: u16, state: &CirnoState) -> Result<(), anyhow::Error> {
+  let outputs = table_outputs(pin, state);
+  if outputs.is_empty() {
+    return Ok(())
+  }
+  let mut inputs: Vec<String> = vec![];
+  for output in outputs.iter() {
+    for label in output.value.inputs() {
+      if !inputs.contains(&label) {
+        inputs.push(label);
+      }
+    }
+  }
+  // header, plus one line per combination of inputs
+  if inputs.len() > MAX_TABLE_INPUTS {
+    move_to(x, y)?;
+    execute!(stdout(), crossterm::style::Print(format!("(truth table too large: {} inputs)", inputs.len())))?;
+    return Ok(())
+  }
+  let rows = 1usize << inputs.len();
+  if usize::from(y) + rows + 2 > usize::from(state.rows) {
+    move_to(x, y)?;
+    execute!(stdout(), crossterm::style::Print(format!("(truth table too large: {} inputs)", inputs.len())))?;
+    return Ok(())
+  }
+  let column = |label: &String| label.len().max(2);
+  let header = format!(
+    "{} | {}",
+    inputs.join(" "),
+    outputs.iter().map(|p| p.label.clone()).collect::<Vec<String>>().join(" "),
+  );
+  move_to(x, y)?;
+  execute!(stdout(), crossterm::style::Print(header))?;
+  for row in 0..rows {
+    let mut voltages: HashMap<String, Voltage> = HashMap::new();
+    let mut line = String::new();
+    for (index, label) in inputs.iter().enumerate() {
+      // the first input is the most significant bit
+      let high = (row >> (inputs.len() - index - 1)) & 1 == 1;
+      voltages.insert(label.clone(), if high { Voltage::High } else { Voltage::Low });
+      line.push_str(&format!("{:>w$} ", u8::from(high), w = column(label)));
+    }
+    line.push('|');
+    for output in outputs.iter() {
+      let voltage = output.clone().calculate_voltage_from_value(&voltages)?;
+      let bit = match voltage {
+        Voltage::High => "1",
+        Voltage::Low => "0",
+        Voltage::Floating => "z",
+        Voltage::Unknown => "x",
+      };
+      line.push_str(&format!(" {:>w$}", bit, w = column(&output.label)));
+    }
+    move_to(x, y + 1 + u16::try_from(row).unwrap())?;
+    execute!(stdout(), crossterm::style::Print(line))?;
+  }
+  Ok(())
+}
+
+fn on_key_h(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  if state.cursor.x > 0 {
+    state.cursor.x -= 1;
+    render(state)?;
+  }
+  Ok(EventResult::Ok)
+}
+
+fn on_key_j(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  if state.cursor.y == 0 {
+    state.cursor.y = 2;
+    render(state)?;
+  }
+  Ok(EventResult::Ok)
+}
+
+fn on_key_k(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  if state.cursor.y == 2 {
+    state.cursor.y = 0;
+    render(state)?;
+  }
+  Ok(EventResult::Ok)
+}
+
+fn on_key_l(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  if state.cursor.x < state.meta.bounds.x.saturating_sub(1) {
+    state.cursor.x += 1;
+    render(state)?;
+  }
+  Ok(EventResult::Ok)
+}
+
+/// Replace the attributes of the selected pin with ones entered via the bar.
+fn on_key_e(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  let (Some(index), Some(old)) = (selected_object(state), selected_pin(state)) else {
+    return Ok(EventResult::Drop)
+  };
+  bar::message(format!("pin {}: ", index_at(old.region.position, state) + 1), state)?;
+  let line = read_line()?;
+  if line.is_empty() {
+    bar::clear(state)?;
+    return Ok(EventResult::Drop)
+  }
+  let mut diagnostics = Diagnostics::default();
+  let objects = parser::parse(&format!(": pin {}", line), "<input>", &mut diagnostics);
+  diagnostics.report()?;
+  let Some(ObjectEnum::Pin(mut pin)) = objects.into_iter().next() else {
+    return Err(CirnoError::InvalidObjectType(line).into())
+  };
+  if pin.explicit_direction.is_some_and(|x| x != pin.inferred_direction()) {
+    return Err(CirnoError::DirectionMismatch(pin.direction().keyword().to_string()).into())
+  }
+  // keep the position, number and comments of the old pin, unless a new comment was given;
+  // the number always follows the position
+  let trailing = pin.comments.trailing.take().or(old.comments.trailing.clone());
+  pin.region = old.region;
+  pin.num = old.num;
+  pin.comments = old.comments.clone();
+  pin.comments.trailing = trailing;
+  let edit = Edit::Change(index, ObjectEnum::Pin(old), ObjectEnum::Pin(pin));
+  state.apply_edit(edit)?;
+  render(state)?;
+  Ok(EventResult::Ok)
+}
+
+fn on_key_u(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  let message = match state.undo()? {
+    Some(description) => format!("undo: {}", description),
+    None => "already at oldest change".to_string(),
+  };
+  render(state)?;
+  bar::message(message, state)?;
+  Ok(EventResult::Ok)
+}
+
+fn on_key_cap_c(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  state.set_mode(Modes::Console)?;
+  Ok(EventResult::Ok)
+}
+
+fn on_key_colon(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  command::read_from_bar(state)
+}
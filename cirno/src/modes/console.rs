@@ -1,4 +1,4 @@
-use crate::{CirnoState, project::{Mode, Modes}, terminal::{EventResult, clear_all, move_to, println}};
+use crate::{CirnoState, project::Mode, terminal::{EventResult, clear_all, move_to, println}};
 use std::collections::HashMap;
 use crossterm::event::{KeyCode, KeyModifiers};
 
@@ -45,6 +45,6 @@ fn on_key_k(_state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
 }
 
 fn on_key_cap_c(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
-  state.set_mode(Modes::Normal)?;
+  state.set_mode(state.last_mode)?;
   Ok(EventResult::Ok)
 }
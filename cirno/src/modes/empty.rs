@@ -1,4 +1,4 @@
-use crate::{CirnoState, bar, command::{self, Command, Splash}, project::Mode, terminal::{EventResult, clear_all}};
+use crate::{CirnoState, bar, command::{self, Command, Splash}, project::{Mode, Modes}, terminal::{EventResult, clear_all}};
 use std::collections::HashMap;
 use crossterm::event::{KeyCode, KeyModifiers};
 
@@ -8,12 +8,15 @@ pub fn get() -> Mode {
     key_event_cb,
     resize_event_cb: handle_resize_event,
     key_commands: HashMap::from([
+      ('C', on_key_cap_c as _),
       (':', on_key_colon as _),
     ]),
   }
 }
 
-fn on_mode_set(_state: &mut CirnoState) -> Result<(), anyhow::Error> {
+fn on_mode_set(state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  clear_all()?;
+  Splash(Vec::new()).execute(state)?;
   Ok(())
 }
 
@@ -33,6 +36,11 @@ fn handle_resize_event(state: &mut CirnoState) -> Result<EventResult, anyhow::Er
   Ok(EventResult::Ok)
 }
 
+fn on_key_cap_c(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  state.set_mode(Modes::Console)?;
+  Ok(EventResult::Ok)
+}
+
 fn on_key_colon(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
-  return command::read_from_bar(state)
+  command::read_from_bar(state)
 }
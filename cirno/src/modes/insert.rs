@@ -0,0 +1,127 @@
+use crate::{bar, chip_types, cursor, diagnostics::Diagnostics, ghost, project::{Chip, Mode, Modes, Object, ObjectEnum, Span}, terminal::{EventResult, read_line_with_completion}, short_chip_type, CirnoState};
+use std::collections::HashMap;
+use crossterm::event::{KeyCode, KeyModifiers};
+
+pub fn get() -> Mode {
+  Mode {
+    mode_set_cb: on_mode_set,
+    key_event_cb,
+    resize_event_cb: handle_resize_event,
+    key_commands: HashMap::from([
+      ('h', on_key_h as _),
+      ('j', on_key_j as _),
+      ('k', on_key_k as _),
+      ('l', on_key_l as _),
+    ]),
+  }
+}
+
+/// Read a chip type entered via the bar, then enter insert mode with a ghost of
+/// that chip at the cursor.
+pub fn read_from_bar(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  bar::message("chip: ".to_string(), state)?;
+  let types = chip_types(state.project.as_deref());
+  let mut line = read_line_with_completion(&types)?;
+  if line.is_empty() {
+    bar::clear(state)?;
+    return Ok(EventResult::Drop)
+  }
+  // allow a chip type to be given without its directory, as long as only one matches
+  if !types.contains(&line) {
+    let matches: Vec<&String> = types.iter().filter(|t| short_chip_type(t.to_string()) == line).collect();
+    if let [t] = matches[..] {
+      line = t.clone();
+    }
+  }
+  let mut diagnostics = Diagnostics::default();
+  if !state.load_cic_data(&line, &Span::default(), &mut diagnostics)? {
+    diagnostics.report()?;
+  }
+  let mut chip = Chip { t: line, ..Default::default() };
+  chip.region.position = state.cursor;
+  chip.set_region_size(state)?;
+  state.ghost = Some(ObjectEnum::Chip(chip));
+  state.set_mode(Modes::Insert)?;
+  Ok(EventResult::Ok)
+}
+
+fn on_mode_set(state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  render(state)?;
+  Ok(())
+}
+
+fn key_event_cb(code: KeyCode, modifiers: KeyModifiers, state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  match code {
+    KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+      bar::message("press <Esc> to leave insert mode".to_string(), state)?;
+    },
+    KeyCode::Enter => return confirm(state),
+    KeyCode::Esc => {
+      state.ghost = None;
+      state.set_mode(Modes::Normal)?;
+      return Ok(EventResult::Ok)
+    },
+    _ => {},
+  }
+  Ok(EventResult::Drop)
+}
+
+fn handle_resize_event(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  state.verify_size()?;
+  render(state)?;
+  Ok(EventResult::Ok)
+}
+
+/// Add the ghost to the project, then return to normal mode.
+/// If the chip cannot be placed here, insert mode is kept so that it can be moved.
+fn confirm(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  let Some(ghost) = state.ghost.clone() else { return Ok(EventResult::Drop) };
+  state.insert_object(ghost)?;
+  state.ghost = None;
+  state.set_mode(Modes::Normal)?;
+  Ok(EventResult::Ok)
+}
+
+/// Render every object, then the ghost on top of them.
+pub fn render(state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  ghost::redraw(None, state)?;
+  let Some(ObjectEnum::Chip(chip)) = state.ghost.as_ref() else { return Ok(()) };
+  let t = chip.t.clone();
+  cursor::render(state)?;
+  bar::message(format!("-- INSERT -- {}", t), state)?;
+  Ok(())
+}
+
+/// Move the cursor, and the ghost with it, by (dx, dy) within the bounds.
+fn move_ghost(dx: i32, dy: i32, state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  let steps = i32::from(state.repeat_amount.max(1));
+  let x = (i32::from(state.cursor.x) + dx * steps).clamp(0, i32::from(state.meta.bounds.x) - 1);
+  let y = (i32::from(state.cursor.y) + dy * steps).clamp(0, i32::from(state.meta.bounds.y) - 1);
+  state.cursor.x = u16::try_from(x).unwrap();
+  state.cursor.y = u16::try_from(y).unwrap();
+  if let Some(ObjectEnum::Chip(chip)) = state.ghost.as_mut() {
+    chip.region.position = state.cursor;
+  }
+  render(state)?;
+  Ok(())
+}
+
+fn on_key_h(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  move_ghost(-1, 0, state)?;
+  Ok(EventResult::Ok)
+}
+
+fn on_key_j(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  move_ghost(0, 1, state)?;
+  Ok(EventResult::Ok)
+}
+
+fn on_key_k(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  move_ghost(0, -1, state)?;
+  Ok(EventResult::Ok)
+}
+
+fn on_key_l(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  move_ghost(1, 0, state)?;
+  Ok(EventResult::Ok)
+}
@@ -0,0 +1,109 @@
+use crate::{CirnoState, bar, project::{Mode, Modes}, terminal::{EventResult, clear_all, move_to}};
+use std::collections::HashMap;
+use std::io::stdout;
+use crossterm::{execute, event::{KeyCode, KeyModifiers}, style::{Color, Colors}};
+
+// a line of text, with the item on it and its color, or None if it is a heading
+pub type Line = (String, Option<(usize, Color)>);
+
+#[derive(Clone, Debug)]
+// the lines shown in list mode, and what to do when the list is closed
+pub struct List {
+  // items are numbered in the order that they appear
+  pub lines: Vec<Line>,
+  pub selected: usize,
+  // called with the selected item if one was picked, or None if the list was left
+  pub close: fn(Option<usize>, &mut CirnoState) -> Result<(), anyhow::Error>,
+}
+
+pub fn get() -> Mode {
+  Mode {
+    mode_set_cb: on_mode_set,
+    key_event_cb,
+    resize_event_cb: handle_resize_event,
+    key_commands: HashMap::from([
+      ('j', on_key_j as _),
+      ('k', on_key_k as _),
+      ('q', on_key_q as _),
+    ]),
+  }
+}
+
+/// Show a list of items to pick one from, starting with the first.
+pub fn open(lines: Vec<Line>, close: fn(Option<usize>, &mut CirnoState) -> Result<(), anyhow::Error>, state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  state.list = Some(List { lines, selected: 0, close });
+  state.set_mode(Modes::List)
+}
+
+fn on_mode_set(state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  render(state)?;
+  Ok(())
+}
+
+fn key_event_cb(code: KeyCode, _modifiers: KeyModifiers, state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  match code {
+    KeyCode::Enter => return close(true, state),
+    KeyCode::Esc => return on_key_q(state),
+    _ => {},
+  }
+  Ok(EventResult::Drop)
+}
+
+fn handle_resize_event(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  render(state)?;
+  Ok(EventResult::Ok)
+}
+
+/// Render the lines of the list, with the selected item highlighted.
+fn render(state: &CirnoState) -> Result<(), anyhow::Error> {
+  clear_all()?;
+  let Some(list) = state.list.as_ref() else { return Ok(()) };
+  // keep the selected item on screen, leaving the last row for the bar
+  let height = usize::from(state.rows.saturating_sub(1)).max(1);
+  let selected = list.lines.iter().position(|(_, x)| x.is_some_and(|(i, _)| i == list.selected)).unwrap_or(0);
+  let skip = (selected + 1).saturating_sub(height);
+  for (row, (line, item)) in list.lines.iter().skip(skip).take(height).enumerate() {
+    move_to(0, u16::try_from(row).unwrap())?;
+    match item {
+      Some((i, _)) if *i == list.selected => {
+        execute!(stdout(), crossterm::style::SetColors(Colors::new(Color::Black, Color::Yellow)))?;
+      },
+      Some((_, color)) => execute!(stdout(), crossterm::style::SetForegroundColor(*color))?,
+      None => {},
+    }
+    execute!(stdout(), crossterm::style::Print(line))?;
+    execute!(stdout(), crossterm::style::ResetColor)?;
+  }
+  bar::message("press <Enter> to jump to a result, or <Esc> to go back".to_string(), state)?;
+  Ok(())
+}
+
+fn on_key_j(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  if let Some(list) = state.list.as_mut() {
+    let len = list.lines.iter().filter(|(_, x)| x.is_some()).count();
+    if list.selected + 1 < len {
+      list.selected += 1;
+    }
+  }
+  render(state)?;
+  Ok(EventResult::Ok)
+}
+
+fn on_key_k(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  if let Some(list) = state.list.as_mut() {
+    list.selected = list.selected.saturating_sub(1);
+  }
+  render(state)?;
+  Ok(EventResult::Ok)
+}
+
+fn on_key_q(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  close(false, state)
+}
+
+/// Close the list, picking the selected item or not.
+fn close(pick: bool, state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  let Some(list) = state.list.take() else { return Ok(EventResult::Drop) };
+  (list.close)(pick.then_some(list.selected), state)?;
+  Ok(EventResult::Ok)
+}
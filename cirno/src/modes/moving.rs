@@ -0,0 +1,145 @@
+use crate::{bar, cursor, error::CirnoError, ghost, history::Edit, project::{Mode, Modes, ObjectEnum, Vector2}, terminal::EventResult, CirnoState};
+use std::collections::HashMap;
+use crossterm::event::{KeyCode, KeyModifiers};
+
+/// The part of an object that has been picked up.
+#[derive(Clone, Copy, Debug)]
+pub enum Handle {
+  // a chip, held at an offset from its top left corner
+  Chip(Vector2),
+  From,
+  To,
+}
+
+/// An object in `source` that is being moved.
+#[derive(Clone, Copy, Debug)]
+pub struct Grab {
+  pub index: usize,
+  pub handle: Handle,
+}
+
+pub fn get() -> Mode {
+  Mode {
+    mode_set_cb: on_mode_set,
+    key_event_cb,
+    resize_event_cb: handle_resize_event,
+    key_commands: HashMap::from([
+      ('h', on_key_h as _),
+      ('j', on_key_j as _),
+      ('k', on_key_k as _),
+      ('l', on_key_l as _),
+      ('m', on_key_m as _),
+    ]),
+  }
+}
+
+/// Pick up the chip or wire endpoint at the cursor, then enter move mode.
+pub fn pick_up(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  let Some(index) = state.source_index_at(state.cursor) else {
+    return Err(CirnoError::NoObjectAtCursor("move".to_string()).into())
+  };
+  let object = state.source.borrow()[index].clone();
+  let handle = match &object {
+    ObjectEnum::Chip(chip) => Handle::Chip(state.cursor - chip.region.position),
+    ObjectEnum::Wire(wire) if wire.from == state.cursor => Handle::From,
+    ObjectEnum::Wire(_) => Handle::To,
+    _ => return Err(CirnoError::ObjectCannotBeMoved.into()),
+  };
+  state.ghost = Some(object);
+  state.grab = Some(Grab { index, handle });
+  state.set_mode(Modes::Move)?;
+  Ok(EventResult::Ok)
+}
+
+fn on_mode_set(state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  render(state)?;
+  Ok(())
+}
+
+fn key_event_cb(code: KeyCode, modifiers: KeyModifiers, state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  match code {
+    KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+      bar::message("press <Esc> to stop moving".to_string(), state)?;
+    },
+    KeyCode::Enter => return on_key_m(state),
+    KeyCode::Esc => {
+      state.ghost = None;
+      state.grab = None;
+      state.set_mode(Modes::Normal)?;
+      return Ok(EventResult::Ok)
+    },
+    _ => {},
+  }
+  Ok(EventResult::Drop)
+}
+
+fn handle_resize_event(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  state.verify_size()?;
+  render(state)?;
+  Ok(EventResult::Ok)
+}
+
+/// Render every object, then the object being moved on top of them.
+pub fn render(state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  ghost::redraw(state.grab.map(|x| x.index), state)?;
+  if state.grab.is_none() {
+    return Ok(())
+  }
+  cursor::render(state)?;
+  bar::message("-- MOVE --".to_string(), state)?;
+  Ok(())
+}
+
+/// Move the cursor by (dx, dy) within the bounds, carrying the grabbed object.
+/// A chip cannot be carried past the top or left edge.
+fn carry(dx: i32, dy: i32, state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  let Some(grab) = state.grab else { return Ok(()) };
+  let min = match grab.handle {
+    Handle::Chip(offset) => offset,
+    _ => Vector2::default(),
+  };
+  let x = (i32::from(state.cursor.x) + dx).clamp(i32::from(min.x), i32::from(state.meta.bounds.x) - 1);
+  let y = (i32::from(state.cursor.y) + dy).clamp(i32::from(min.y), i32::from(state.meta.bounds.y) - 1);
+  state.cursor.x = u16::try_from(x).unwrap();
+  state.cursor.y = u16::try_from(y).unwrap();
+  match (state.ghost.as_mut(), grab.handle) {
+    (Some(ObjectEnum::Chip(chip)), Handle::Chip(offset)) => chip.region.position = state.cursor - offset,
+    (Some(ObjectEnum::Wire(wire)), Handle::From) => wire.from = state.cursor,
+    (Some(ObjectEnum::Wire(wire)), Handle::To) => wire.to = state.cursor,
+    _ => {},
+  }
+  render(state)?;
+  Ok(())
+}
+
+fn on_key_h(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  carry(-1, 0, state)?;
+  Ok(EventResult::Ok)
+}
+
+fn on_key_j(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  carry(0, 1, state)?;
+  Ok(EventResult::Ok)
+}
+
+fn on_key_k(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  carry(0, -1, state)?;
+  Ok(EventResult::Ok)
+}
+
+fn on_key_l(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  carry(1, 0, state)?;
+  Ok(EventResult::Ok)
+}
+
+/// Put the grabbed object down where it is, then return to normal mode.
+/// If it cannot be put down here, move mode is kept so that it can be moved again.
+fn on_key_m(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  let (Some(grab), Some(object)) = (state.grab, state.ghost.clone()) else { return Ok(EventResult::Drop) };
+  let before = state.source.borrow()[grab.index].clone();
+  state.apply_edit(Edit::Move(grab.index, before, object))?;
+  state.ghost = None;
+  state.grab = None;
+  state.set_mode(Modes::Normal)?;
+  Ok(EventResult::Ok)
+}
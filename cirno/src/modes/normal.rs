@@ -1,4 +1,4 @@
-use crate::{bar, command, cursor, error::{CirnoError, try_to}, project::{Mode, Modes}, search, terminal::{clear_all, read_key_presses, EventResult}, CirnoState};
+use crate::{bar, command, cursor, diagnostics::Diagnostics, error::{CirnoError, try_to}, modes::{insert, moving, wire}, project::{Mode, Modes, ObjectEnum}, search, terminal::{clear_all, read_key_presses, EventResult}, CirnoState};
 use std::collections::HashMap;
 use crossterm::event::{KeyCode, KeyModifiers};
 
@@ -12,18 +12,29 @@ pub fn get() -> Mode {
       ('j', on_key_j as _),
       ('k', on_key_k as _),
       ('l', on_key_l as _),
+      ('d', on_key_x as _),
+      ('i', on_key_i as _),
+      ('m', on_key_m as _),
+      ('n', on_key_n as _),
       ('p', on_key_p as _),
+      ('u', on_key_u as _),
+      ('w', on_key_w as _),
+      ('x', on_key_x as _),
       ('C', on_key_cap_c as _),
       ('L', on_key_cap_l as _),
+      ('N', on_key_cap_n as _),
       (':', on_key_colon as _),
       ('/', on_key_slash as _),
+      ('*', on_key_asterisk as _),
     ]),
   }
 }
 
 fn on_mode_set(state: &mut CirnoState) -> Result<(), anyhow::Error> {
   clear_all()?;
-  state.verify()?;
+  let mut diagnostics = Diagnostics::default();
+  state.verify(&mut diagnostics);
+  diagnostics.report()?;
   state.render()?;
   Ok(())
 }
@@ -33,6 +44,16 @@ fn key_event_cb(code: KeyCode, modifiers: KeyModifiers, state: &mut CirnoState)
     if c == 'c' && modifiers.contains(KeyModifiers::CONTROL) {
       bar::message("type  :q  and press <Enter> to exit cirno".to_string(), state)?;
     }
+    if c == 'r' && modifiers.contains(KeyModifiers::CONTROL) {
+      let message = match state.redo()? {
+        Some(description) => format!("redo: {}", description),
+        None => "already at newest change".to_string(),
+      };
+      clear_all()?;
+      state.render()?;
+      bar::message(message, state)?;
+      return Ok(EventResult::Ok)
+    }
     if let '0' ..= '9' = c {
       try_to(update_repeat_amount(c, state), state)?;
     }
@@ -86,6 +107,19 @@ fn on_key_l(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
   Ok(EventResult::Ok)
 }
 
+fn on_key_i(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  insert::read_from_bar(state)
+}
+
+fn on_key_m(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  moving::pick_up(state)
+}
+
+fn on_key_n(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  search::step(true, state)?;
+  Ok(EventResult::Ok)
+}
+
 fn on_key_p(_state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
   if let Some(sequence) = read_key_presses(3)? {
     crate::logger::debug(format!("{:?}", sequence));
@@ -93,6 +127,39 @@ fn on_key_p(_state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
   Ok(EventResult::Ok)
 }
 
+fn on_key_u(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  let message = match state.undo()? {
+    Some(description) => format!("undo: {}", description),
+    None => "already at oldest change".to_string(),
+  };
+  clear_all()?;
+  state.render()?;
+  bar::message(message, state)?;
+  Ok(EventResult::Ok)
+}
+
+fn on_key_w(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  wire::enter(state)
+}
+
+/// Delete the chip, wire or net at the cursor.
+fn on_key_x(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  let Some(index) = state.source_index_at(state.cursor) else {
+    return Err(CirnoError::NoObjectAtCursor("delete".to_string()).into())
+  };
+  let kind = match state.source.borrow()[index] {
+    ObjectEnum::Chip(_) => "chip",
+    ObjectEnum::Net(_) => "net",
+    ObjectEnum::Wire(_) => "wire",
+    _ => "object",
+  };
+  state.delete_object(index)?;
+  clear_all()?;
+  state.render()?;
+  bar::message(format!("{} deleted", kind), state)?;
+  Ok(EventResult::Ok)
+}
+
 fn on_key_cap_c(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
   state.set_mode(Modes::Console)?;
   Ok(EventResult::Ok)
@@ -103,10 +170,20 @@ fn on_key_cap_l(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
   Ok(EventResult::Ok)
 }
 
+fn on_key_cap_n(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  search::step(false, state)?;
+  Ok(EventResult::Ok)
+}
+
 fn on_key_colon(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
-  return command::read_from_bar(state)
+  command::read_from_bar(state)
 }
 
 fn on_key_slash(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
-  return search::read_from_bar(state)
+  search::read_from_bar(state)
+}
+
+fn on_key_asterisk(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  search::query_net(state)?;
+  Ok(EventResult::Ok)
 }
@@ -0,0 +1,134 @@
+use crate::{bar, color_from_char, color_to_string, ghost, project::{Mode, Modes, ObjectEnum, Wire}, terminal::{EventResult, read_key_presses}, CirnoState};
+use std::collections::HashMap;
+use crossterm::event::{KeyCode, KeyModifiers};
+
+pub fn get() -> Mode {
+  Mode {
+    mode_set_cb: on_mode_set,
+    key_event_cb,
+    resize_event_cb: handle_resize_event,
+    key_commands: HashMap::from([
+      ('h', on_key_h as _),
+      ('j', on_key_j as _),
+      ('k', on_key_k as _),
+      ('l', on_key_l as _),
+      ('c', on_key_c as _),
+      ('w', on_key_w as _),
+    ]),
+  }
+}
+
+/// Enter wire mode, with no endpoint marked yet.
+pub fn enter(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  state.ghost = None;
+  state.set_mode(Modes::Wire)?;
+  Ok(EventResult::Ok)
+}
+
+fn on_mode_set(state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  render(state)?;
+  Ok(())
+}
+
+fn key_event_cb(code: KeyCode, modifiers: KeyModifiers, state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  match code {
+    KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+      bar::message("press <Esc> to leave wire mode".to_string(), state)?;
+    },
+    KeyCode::Enter => return on_key_w(state),
+    KeyCode::Esc => {
+      // drop the marked endpoint first, then leave
+      if state.ghost.take().is_some() {
+        render(state)?;
+      } else {
+        state.set_mode(Modes::Normal)?;
+      }
+      return Ok(EventResult::Ok)
+    },
+    _ => {},
+  }
+  Ok(EventResult::Drop)
+}
+
+fn handle_resize_event(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  state.verify_size()?;
+  render(state)?;
+  Ok(EventResult::Ok)
+}
+
+/// Render every object, then the wire being drawn on top of them.
+pub fn render(state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  ghost::redraw(None, state)?;
+  let color = color_to_string(state.wire_color);
+  let Some(ObjectEnum::Wire(wire)) = state.ghost.as_ref() else {
+    bar::message(format!("-- WIRE -- {}", color), state)?;
+    return Ok(())
+  };
+  let (x, y) = (wire.from.x, wire.from.y);
+  bar::message(format!("-- WIRE -- {} from ({}, {})", color, x, y), state)?;
+  Ok(())
+}
+
+/// Move the cursor by (dx, dy) within the bounds, dragging the loose end of the
+/// wire being drawn.
+fn move_end(dx: i32, dy: i32, state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  let x = (i32::from(state.cursor.x) + dx).clamp(0, i32::from(state.meta.bounds.x) - 1);
+  let y = (i32::from(state.cursor.y) + dy).clamp(0, i32::from(state.meta.bounds.y) - 1);
+  state.cursor.x = u16::try_from(x).unwrap();
+  state.cursor.y = u16::try_from(y).unwrap();
+  if let Some(ObjectEnum::Wire(wire)) = state.ghost.as_mut() {
+    wire.to = state.cursor;
+  }
+  render(state)?;
+  Ok(())
+}
+
+fn on_key_h(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  move_end(-1, 0, state)?;
+  Ok(EventResult::Ok)
+}
+
+fn on_key_j(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  move_end(0, 1, state)?;
+  Ok(EventResult::Ok)
+}
+
+fn on_key_k(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  move_end(0, -1, state)?;
+  Ok(EventResult::Ok)
+}
+
+fn on_key_l(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  move_end(1, 0, state)?;
+  Ok(EventResult::Ok)
+}
+
+/// Pick the color of the next wire using the same letters as wire searches.
+fn on_key_c(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  bar::message("color (r/g/y/b/m/c): ".to_string(), state)?;
+  let color = read_key_presses(1)?.and_then(|s| s.chars().next()).and_then(color_from_char);
+  if let Some(color) = color {
+    state.wire_color = color;
+    if let Some(ObjectEnum::Wire(wire)) = state.ghost.as_mut() {
+      wire.color = color;
+    }
+  }
+  render(state)?;
+  Ok(EventResult::Ok)
+}
+
+/// Mark the start of a wire at the cursor, or drop its end there.
+fn on_key_w(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
+  match state.ghost.clone() {
+    Some(ObjectEnum::Wire(wire)) => {
+      state.insert_object(ObjectEnum::Wire(wire))?;
+      state.ghost = None;
+    },
+    _ => {
+      let wire = Wire { color: state.wire_color, from: state.cursor, to: state.cursor, ..Default::default() };
+      state.ghost = Some(ObjectEnum::Wire(wire));
+    },
+  }
+  render(state)?;
+  Ok(EventResult::Ok)
+}
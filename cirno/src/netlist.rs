@@ -0,0 +1,263 @@
+use crate::project::{Meta, ObjectEnum, Vector2, Wire};
+use std::collections::{HashMap, VecDeque};
+
+/// The electrical nets on a board, found by joining every pin, wire endpoint and
+/// rail hole that are connected by wires, rails or terminal strips.
+#[derive(Clone, Debug, Default)]
+pub struct Netlist {
+  // the net of each terminal strip, by the position of its top hole
+  strips: HashMap<Vector2, usize>,
+  // the points that were joined into each net
+  pub members: Vec<Vec<Vector2>>,
+  // the type of the rail that each net includes, if any
+  pub rails: Vec<Option<String>>,
+  // the nets that join rails of different types, shorting them together
+  pub shorts: Vec<usize>,
+  // the row and type of each rail
+  rail_rows: Vec<(u16, String)>,
+  meta: Meta,
+}
+
+/// A disjoint set forest over point indices.
+struct UnionFind {
+  parents: Vec<usize>,
+}
+
+impl UnionFind {
+  fn new(len: usize) -> UnionFind {
+    UnionFind { parents: (0..len).collect() }
+  }
+  fn find(&mut self, i: usize) -> usize {
+    let mut root = i;
+    while self.parents[root] != root {
+      root = self.parents[root];
+    }
+    // compress the path, so that later finds are quicker
+    let mut i = i;
+    while self.parents[i] != root {
+      let next = self.parents[i];
+      self.parents[i] = root;
+      i = next;
+    }
+    root
+  }
+  fn union(&mut self, a: usize, b: usize) {
+    let (ra, rb) = (self.find(a), self.find(b));
+    if ra != rb {
+      self.parents[rb] = ra;
+    }
+  }
+}
+
+impl Netlist {
+  /// Build the netlist of a board from its converted objects.
+  pub fn build(objects: &[ObjectEnum], meta: &Meta) -> Netlist {
+    let mut points: Vec<Vector2> = vec![];
+    let mut indices: HashMap<Vector2, usize> = HashMap::new();
+    let mut add = |position: Vector2, points: &mut Vec<Vector2>| -> usize {
+      *indices.entry(position).or_insert_with(|| {
+        points.push(position);
+        points.len() - 1
+      })
+    };
+    // points, and the connections made by wires and rails
+    let mut joins: Vec<(usize, usize)> = vec![];
+    let mut rail_points: Vec<(usize, String)> = vec![];
+    let mut rail_rows: Vec<(u16, String)> = vec![];
+    for object in objects.iter() {
+      match object {
+        ObjectEnum::Pin(pin) => { add(pin.region.position, &mut points); },
+        ObjectEnum::Wire(wire) => {
+          let from = add(wire.from, &mut points);
+          let to = add(wire.to, &mut points);
+          joins.push((from, to));
+        },
+        ObjectEnum::Net(net) => {
+          let y = net.region.position.y;
+          let first = add(Vector2 { x: 0, y }, &mut points);
+          for x in 1..meta.bounds.x {
+            let i = add(Vector2 { x, y }, &mut points);
+            joins.push((first, i));
+          }
+          rail_points.push((first, net.t.clone()));
+          rail_rows.push((y, net.t.clone()));
+        },
+        _ => {},
+      }
+    }
+    let mut forest = UnionFind::new(points.len());
+    for (a, b) in joins {
+      forest.union(a, b);
+    }
+    // connections made by terminal strips
+    let mut strip_points: HashMap<Vector2, usize> = HashMap::new();
+    for (i, point) in points.iter().enumerate() {
+      let strip = meta.strip_at(*point).position;
+      let first = *strip_points.entry(strip).or_insert(i);
+      forest.union(first, i);
+    }
+    // number the nets in the order that their first point was found
+    let mut ids: HashMap<usize, usize> = HashMap::new();
+    let mut netlist = Netlist { rail_rows, meta: meta.clone(), ..Default::default() };
+    for (i, point) in points.iter().enumerate() {
+      let root = forest.find(i);
+      let id = *ids.entry(root).or_insert_with(|| {
+        netlist.members.push(vec![]);
+        netlist.rails.push(None);
+        netlist.members.len() - 1
+      });
+      netlist.members[id].push(*point);
+    }
+    for (strip, i) in strip_points {
+      netlist.strips.insert(strip, ids[&forest.find(i)]);
+    }
+    for (i, t) in rail_points {
+      let id = ids[&forest.find(i)];
+      if netlist.rails[id].as_ref().is_some_and(|x| *x != t) && !netlist.shorts.contains(&id) {
+        netlist.shorts.push(id);
+      }
+      netlist.rails[id] = Some(t);
+    }
+    netlist
+  }
+  /// Return the ID of the net that a position is on, if anything is connected to it.
+  pub fn net_at(&self, position: Vector2) -> Option<usize> {
+    self.strips.get(&self.meta.strip_at(position).position).copied()
+  }
+  /// Return whether anything other than the position itself is on its net.
+  pub fn is_connected(&self, position: Vector2) -> bool {
+    self.net_at(position).is_some_and(|id| self.members[id].len() > 1)
+  }
+  /// Return the place that a position is joined to without any wires: the first hole of
+  /// its rail or terminal strip, or the position itself.
+  pub fn place(&self, position: Vector2) -> Vector2 {
+    match self.rail_rows.iter().any(|(y, _)| *y == position.y) {
+      true => Vector2 { x: 0, y: position.y },
+      false => self.meta.strip_at(position).position,
+    }
+  }
+  /// Return the place of every rail of a type, e.g. `vcc`.
+  pub fn rail_places(&self, t: &str) -> Vec<Vector2> {
+    self.rail_rows.iter().filter(|(_, x)| x == t).map(|(y, _)| Vector2 { x: 0, y: *y }).collect()
+  }
+  /// Return the fewest wires that join any of the places in `from` to any in `to`.
+  pub fn path(&self, wires: &[Wire], from: &[Vector2], to: &[Vector2]) -> Vec<Wire> {
+    // the wire that each place was first reached through
+    let mut reached: HashMap<Vector2, Option<usize>> = from.iter().map(|x| (*x, None)).collect();
+    let mut queue: VecDeque<Vector2> = from.iter().copied().collect();
+    while let Some(place) = queue.pop_front() {
+      if to.contains(&place) {
+        let mut path: Vec<Wire> = vec![];
+        let mut place = place;
+        while let Some(Some(i)) = reached.get(&place) {
+          path.push(wires[*i].clone());
+          let (a, b) = (self.place(wires[*i].from), self.place(wires[*i].to));
+          place = if a == place { b } else { a };
+        }
+        path.reverse();
+        return path
+      }
+      for (i, wire) in wires.iter().enumerate() {
+        let (a, b) = (self.place(wire.from), self.place(wire.to));
+        let next = match (a == place, b == place) {
+          (true, _) => b,
+          (_, true) => a,
+          _ => continue,
+        };
+        if let std::collections::hash_map::Entry::Vacant(e) = reached.entry(next) {
+          e.insert(Some(i));
+          queue.push_back(next);
+        }
+      }
+    }
+    vec![]
+  }
+  /// Return the type of the rail that a net includes, e.g. `vcc`.
+  pub fn rail(&self, id: usize) -> Option<&str> {
+    self.rails.get(id)?.as_deref()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{CirnoState, diagnostics::Diagnostics, project::{ObjectEnum, Vector2, Wire}};
+  use crossterm::style::Color;
+  use super::Netlist;
+
+  const PROJECT: &str = "\
+: meta bounds 10 12 gap 5 strip 3
+: net type vcc y 0
+: net type gnd y 11
+: wire color red from 1 0 to 1 2
+: wire color green from 1 4 to 3 4
+: wire color blue from 3 6 to 3 11
+";
+
+  fn build(contents: &str) -> (Netlist, Vec<Wire>) {
+    let mut state = CirnoState::with_size(0, 0);
+    let mut diagnostics = Diagnostics::default();
+    crate::load(&mut state, contents, "test.cip", &mut diagnostics).unwrap();
+    assert!(!diagnostics.has_errors(), "{:?}", diagnostics.items);
+    let objects = state.objects.borrow();
+    let wires = objects
+      .iter()
+      .filter_map(|x| match x {
+        ObjectEnum::Wire(wire) => Some(wire.clone()),
+        _ => None,
+      })
+      .collect();
+    (Netlist::build(&objects, &state.meta), wires)
+  }
+
+  fn at(x: u16, y: u16) -> Vector2 {
+    Vector2 { x, y }
+  }
+
+  #[test]
+  fn nets_join_through_strips_and_wires() {
+    let (netlist, _) = build(PROJECT);
+    let vcc = netlist.net_at(at(1, 3)).unwrap();
+    // along the strip, through the wire to the next strip, and up the rail
+    assert_eq!(netlist.net_at(at(3, 2)), Some(vcc));
+    assert_eq!(netlist.net_at(at(7, 0)), Some(vcc));
+    let gnd = netlist.net_at(at(3, 8)).unwrap();
+    assert_eq!(netlist.net_at(at(0, 11)), Some(gnd));
+    assert_ne!(vcc, gnd);
+    // nothing is plugged in or wired here
+    assert_eq!(netlist.net_at(at(5, 3)), None);
+    assert!(!netlist.is_connected(at(5, 3)));
+    assert!(netlist.is_connected(at(1, 3)));
+  }
+
+  #[test]
+  fn rails() {
+    let (netlist, _) = build(PROJECT);
+    let vcc = netlist.net_at(at(3, 3)).unwrap();
+    let gnd = netlist.net_at(at(3, 7)).unwrap();
+    assert_eq!(netlist.rail(vcc), Some("vcc"));
+    assert_eq!(netlist.rail(gnd), Some("gnd"));
+    assert_eq!(netlist.rail_places("gnd"), vec![at(0, 11)]);
+    assert!(netlist.shorts.is_empty());
+  }
+
+  #[test]
+  fn shorts() {
+    let contents = format!("{}: wire color yellow from 3 3 to 3 7\n", PROJECT);
+    let (netlist, _) = build(&contents);
+    let net = netlist.net_at(at(0, 0)).unwrap();
+    assert_eq!(netlist.net_at(at(0, 11)), Some(net));
+    assert_eq!(netlist.shorts, vec![net]);
+  }
+
+  #[test]
+  fn path() {
+    let contents = format!("{}: wire color yellow from 3 3 to 3 7\n", PROJECT);
+    let (netlist, wires) = build(&contents);
+    let path = netlist.path(&wires, &netlist.rail_places("vcc"), &netlist.rail_places("gnd"));
+    let colors: Vec<Color> = path.iter().map(|x| x.color).collect();
+    assert_eq!(colors, vec![Color::Red, Color::Green, Color::Yellow, Color::Blue]);
+    // without the yellow wire, nothing joins the rails
+    let (netlist, wires) = build(PROJECT);
+    assert!(netlist.path(&wires, &netlist.rail_places("vcc"), &netlist.rail_places("gnd")).is_empty());
+  }
+}
@@ -1,8 +1,8 @@
-use crate::{CirnoError, project::*};
+use crate::{CirnoError, diagnostics::Diagnostics, project::*};
 use crossterm::style::Color;
 use logos::Logos;
 
-#[derive(Logos, Debug, PartialEq)]
+#[derive(Logos, Clone, Debug, PartialEq)]
 #[logos(skip r"[ \t\n\f]+")]
 // token types
 pub enum Token {
@@ -15,7 +15,15 @@ pub enum Token {
   #[token(".")]
   Ender,
   #[token(":")]
-  Separator
+  Separator,
+  #[token("(")]
+  Open,
+  #[token(")")]
+  Close,
+  #[regex("\"[^\"\n]*\"")]
+  Text,
+  #[regex("#[^\n]*")]
+  Comment,
 }
 
 impl std::fmt::Display for Token {
@@ -24,8 +32,6 @@ impl std::fmt::Display for Token {
   }
 }
 
-// TODO: since parse() creates a new Token::lexer for each line,
-// the phrasing of the None arms in the below macros is a bit weird
 // TODO: wish these two macros could be combined into one
 
 /// expect_number!(lexer)
@@ -34,7 +40,7 @@ macro_rules! expect_number {
     match $x.next() {
       Some(Ok(Token::Number)) => Ok($x.slice().parse().unwrap()),
       Some(Ok(u)) => Err(CirnoError::UnexpectedTokenExpectedNumber(u)),
-      Some(Err(_e)) => Err(CirnoError::UnrecognizedToken),
+      Some(Err(_e)) => Err(CirnoError::UnrecognizedToken($x.slice().to_string())),
       None => Err(CirnoError::OutOfTokensExpectedNumber),
     }
   }}
@@ -46,30 +52,74 @@ macro_rules! expect_token {
     match $x.next() {
       Some(Ok($p)) => Ok($x.slice().to_string()),
       Some(Ok(u)) => Err(CirnoError::UnexpectedToken($p, u)),
-      Some(Err(_e)) => Err(CirnoError::UnrecognizedToken),
+      Some(Err(_e)) => Err(CirnoError::UnrecognizedToken($x.slice().to_string())),
       None => Err(CirnoError::OutOfTokens($p)),
     }
   }}
 }
 
-pub fn parse(contents: &str) -> Result<Vec<ObjectEnum>, anyhow::Error> {
+/// Parse the contents of a file into a list of objects.
+/// `file` is only used to give each object and diagnostic a span.
+/// A line that fails to parse is reported to `diagnostics` and skipped.
+pub fn parse(contents: &str, file: &str, diagnostics: &mut Diagnostics) -> Vec<ObjectEnum> {
   // open file
   let mut ast: Vec<ObjectEnum> = vec![];
+  let mut comments = Comments::default();
   // for each line in the file
-  for line in contents.lines() {
-    // tokenize the line if it is not blank
-    if line.eq("") {
+  for (index, line) in contents.lines().enumerate() {
+    let (line, comment) = split_comment(line);
+    // blank lines and comment lines are kept with the object that follows them
+    if line.trim().is_empty() {
+      comments.before.push(format!("{}{}", line, comment.unwrap_or("")).trim_end().to_string());
       continue;
     }
+    // tokenize the line if it is not blank
     let mut lex = Token::lexer(line);
-    // move to the first token, which should be a Token::Separator
-    expect_token!(lex, Token::Separator)?;
-    // parse an object into the AST
-    let object_type = expect_token!(lex, Token::Keyword)?;
-    let object = parse_object(&object_type, &mut lex)?;
+    let mut object = match parse_line(&mut lex) {
+      Ok(object) => object,
+      Err(e) => {
+        // point at the offending token, or at the end of the line if there are none left
+        let columns = match e {
+          CirnoError::OutOfTokens(_) | CirnoError::OutOfTokensExpectedNumber => line.len()..line.len(),
+          _ => lex.span(),
+        };
+        diagnostics.error(&Span::new(file, index + 1, columns), e);
+        continue;
+      },
+    };
+    object.set_span(Span::new(file, index + 1, 0..line.trim_end().len()));
+    comments.trailing = comment.map(|x| x.trim_end().to_string());
+    object.set_comments(std::mem::take(&mut comments));
     ast.push(object);
   }
-  Ok(ast)
+  // anything after the last object is kept with it
+  if let Some(object) = ast.last_mut() {
+    let mut last = object.get_comments().clone();
+    last.after = comments.before;
+    object.set_comments(last);
+  }
+  ast
+}
+
+/// Split a line into the part before its comment, and the comment itself.
+fn split_comment(line: &str) -> (&str, Option<&str>) {
+  let mut lex = Token::lexer(line);
+  while let Some(token) = lex.next() {
+    if let Ok(Token::Comment) = token {
+      let start = lex.span().start;
+      return (&line[..start], Some(&line[start..]))
+    }
+  }
+  (line, None)
+}
+
+/// Parse a single line into an object.
+fn parse_line(lexer: &mut logos::Lexer<'_, Token>) -> Result<ObjectEnum, CirnoError> {
+  // move to the first token, which should be a Token::Separator
+  expect_token!(lexer, Token::Separator)?;
+  // parse an object into the AST
+  let object_type = expect_token!(lexer, Token::Keyword)?;
+  parse_object(&object_type, lexer)
 }
 
 fn parse_attribute(token: &str, lexer: &mut logos::Lexer<'_, Token>) -> Result<Attribute, CirnoError> {
@@ -84,24 +134,70 @@ fn parse_attribute(token: &str, lexer: &mut logos::Lexer<'_, Token>) -> Result<A
       let c = parse_attribute_color(&c_string)?;
       Ok(Attribute::Color(c))
     },
+    "datasheet" => {
+      let url = expect_token!(lexer, Token::Text)?;
+      Ok(Attribute::Datasheet(unquote(&url)))
+    },
+    "desc" => {
+      let description = expect_token!(lexer, Token::Text)?;
+      Ok(Attribute::Description(unquote(&description)))
+    },
+    "dir" => {
+      let d = expect_token!(lexer, Token::Keyword)?;
+      match Direction::from_keyword(&d) {
+        Some(direction) => Ok(Attribute::Direction(direction)),
+        None => Err(CirnoError::InvalidValueForAttribute(d, "dir".to_string())),
+      }
+    },
+    "family" => {
+      let f = expect_token!(lexer, Token::Keyword)?;
+      match Family::from_keyword(&f) {
+        Some(family) => Ok(Attribute::Family(family)),
+        None => Err(CirnoError::InvalidValueForAttribute(f, "family".to_string())),
+      }
+    },
     "from" => {
       let x: u16 = expect_number!(lexer)?;
       let y: u16 = expect_number!(lexer)?;
       Ok(Attribute::From(Vector2 { x, y }))
     },
+    "gap" => {
+      let y: u16 = expect_number!(lexer)?;
+      Ok(Attribute::Gap(y))
+    },
     "label" => {
       let label = expect_token!(lexer, Token::Identifier)?;
       Ok(Attribute::Label(label))
     },
+    "name" => {
+      let name = expect_token!(lexer, Token::Keyword)?;
+      Ok(Attribute::Name(name))
+    },
+    "oc" => Ok(Attribute::Output(Output::OpenCollector)),
+    "oe" | "noe" => {
+      let label = expect_token!(lexer, Token::Identifier)?;
+      Ok(Attribute::Output(Output::TriState(Control { label, active_low: token == "noe" })))
+    },
     "num" => {
       let num: u16 = expect_number!(lexer)?;
       Ok(Attribute::Num(num))
     },
+    "package" => {
+      let p = expect_token!(lexer, Token::Keyword)?;
+      match Package::from_keyword(&p) {
+        Some(package) => Ok(Attribute::Package(package)),
+        None => Err(CirnoError::InvalidValueForAttribute(p, "package".to_string())),
+      }
+    },
     "pos" => {
       let x: u16 = expect_number!(lexer)?;
       let y: u16 = expect_number!(lexer)?;
       Ok(Attribute::Position(Vector2 { x, y }))
     },
+    "strip" => {
+      let height: u16 = expect_number!(lexer)?;
+      Ok(Attribute::Strip(height))
+    },
     "to" => {
       let x: u16 = expect_number!(lexer)?;
       let y: u16 = expect_number!(lexer)?;
@@ -124,6 +220,11 @@ fn parse_attribute(token: &str, lexer: &mut logos::Lexer<'_, Token>) -> Result<A
   }
 }
 
+/// Remove the quotes around a piece of text.
+fn unquote(text: &str) -> String {
+  text.trim_matches('"').to_string()
+}
+
 fn parse_attribute_color(token: &str) -> Result<Color, CirnoError> {
   match token {
     "red" => Ok(Color::Red),
@@ -138,27 +239,11 @@ fn parse_attribute_color(token: &str) -> Result<Color, CirnoError> {
 
 fn parse_attribute_value(token: &str, lexer: &mut logos::Lexer<'_, Token>) -> Result<Value, CirnoError> {
   match token {
-    "and" => {
-      let values: Vec<String> = consume_until_ender(lexer)?;
-      Ok(Value::And(values))
-    },
-    "nand" => {
-      let values: Vec<String> = consume_until_ender(lexer)?;
-      Ok(Value::Nand(values))
-    },
-    "not" => {
-      let label = expect_token!(lexer, Token::Identifier)?;
-      Ok(Value::Not(label))
-    },
-    "or" => {
-      let values: Vec<String> = consume_until_ender(lexer)?;
-      Ok(Value::Or(values))
-    },
-    "xor" => {
-      let a = expect_token!(lexer, Token::Identifier)?;
-      let b = expect_token!(lexer, Token::Identifier)?;
-      Ok(Value::Xor(a, b))
-    },
+    "and" | "nand" | "nor" | "not" | "or" | "xor" => parse_gate(token, lexer),
+    "dff" => Ok(Value::Sequential(Box::new(parse_sequential(Storage::D, lexer)?))),
+    "jkff" => Ok(Value::Sequential(Box::new(parse_sequential(Storage::Jk, lexer)?))),
+    "latch" => Ok(Value::Sequential(Box::new(parse_sequential(Storage::Latch, lexer)?))),
+    "table" => Ok(Value::Table(Box::new(parse_table(lexer)?))),
     "gnd" => Ok(Value::Gnd),
     "vcc" => Ok(Value::Vcc),
     "nc" => Ok(Value::Nc),
@@ -166,21 +251,200 @@ fn parse_attribute_value(token: &str, lexer: &mut logos::Lexer<'_, Token>) -> Re
   }
 }
 
-// TODO: should this function return Ok on an ender and Err if it reaches None?
-fn consume_until_ender(lexer: &mut logos::Lexer<'_, Token>) -> Result<Vec<String>, CirnoError> { // TODO: anyhow?
-  let mut values: Vec<String> = vec![];
-  while let Some(_token) = lexer.next() {
-    if lexer.slice() == "." {
-      break;
+/// Parse a gate at the top level of a value, e.g. `nor (and '1a '1b) (and '1c '1d) .`.
+/// A gate whose operands are all labels keeps its flat form.
+fn parse_gate(op: &str, lexer: &mut logos::Lexer<'_, Token>) -> Result<Value, CirnoError> {
+  let operands = match op {
+    "not" => vec![parse_operand(lexer)?],
+    "xor" => parse_xor_operands(lexer)?,
+    _ => parse_operands(lexer, Token::Ender)?,
+  };
+  let labels: Option<Vec<String>> = operands
+    .iter()
+    .map(|x| match x {
+      Expression::Label(label) => Some(label.clone()),
+      _ => None,
+    })
+    .collect();
+  match (op, labels) {
+    ("and", Some(labels)) => Ok(Value::And(labels)),
+    ("nand", Some(labels)) => Ok(Value::Nand(labels)),
+    ("nor", Some(labels)) => Ok(Value::Nor(labels)),
+    ("not", Some(mut labels)) => Ok(Value::Not(labels.remove(0))),
+    ("or", Some(labels)) => Ok(Value::Or(labels)),
+    ("xor", Some(labels)) => Ok(Value::Xor(labels)),
+    _ => Ok(Value::Expression(gate(op, operands)?)),
+  }
+}
+
+/// Return the expression for a gate with the given operands.
+fn gate(op: &str, mut operands: Vec<Expression>) -> Result<Expression, CirnoError> {
+  match op {
+    "and" => Ok(Expression::And(operands)),
+    "nand" => Ok(Expression::Not(Box::new(Expression::And(operands)))),
+    "nor" => Ok(Expression::Not(Box::new(Expression::Or(operands)))),
+    "not" if operands.len() == 1 => Ok(Expression::Not(Box::new(operands.remove(0)))),
+    "or" => Ok(Expression::Or(operands)),
+    "xor" => Ok(Expression::Xor(operands)),
+    o => Err(CirnoError::InvalidValueAttribute(o.to_string())),
+  }
+}
+
+/// Parse a single operand: a label, or a gate in parentheses, e.g. `(and '1a '1b)`.
+fn parse_operand(lexer: &mut logos::Lexer<'_, Token>) -> Result<Expression, CirnoError> {
+  match lexer.next() {
+    Some(Ok(Token::Identifier)) => Ok(Expression::Label(lexer.slice().to_string())),
+    Some(Ok(Token::Open)) => {
+      let op = expect_token!(lexer, Token::Keyword)?;
+      let operands = parse_operands(lexer, Token::Close)?;
+      gate(&op, operands)
+    },
+    Some(Ok(u)) => Err(CirnoError::UnexpectedToken(Token::Identifier, u)),
+    Some(Err(_e)) => Err(CirnoError::UnrecognizedToken(lexer.slice().to_string())),
+    None => Err(CirnoError::OutOfTokens(Token::Identifier)),
+  }
+}
+
+/// Parse operands until `closer`, which is consumed.
+fn parse_operands(lexer: &mut logos::Lexer<'_, Token>, closer: Token) -> Result<Vec<Expression>, CirnoError> {
+  let mut operands: Vec<Expression> = vec![];
+  loop {
+    let mut peek = lexer.clone();
+    match peek.next() {
+      Some(Ok(t)) if t == closer => {
+        *lexer = peek;
+        return Ok(operands)
+      },
+      None => return Err(CirnoError::OutOfTokens(closer)),
+      _ => operands.push(parse_operand(lexer)?),
+    }
+  }
+}
+
+/// Parse the operands of a top-level xor.
+/// Two operands may be given without an ender, as they always used to be.
+fn parse_xor_operands(lexer: &mut logos::Lexer<'_, Token>) -> Result<Vec<Expression>, CirnoError> {
+  let mut operands = vec![parse_operand(lexer)?, parse_operand(lexer)?];
+  let mut peek = lexer.clone();
+  match peek.next() {
+    Some(Ok(Token::Ender)) => *lexer = peek,
+    Some(Ok(Token::Identifier | Token::Open)) => operands.append(&mut parse_operands(lexer, Token::Ender)?),
+    _ => {},
+  }
+  Ok(operands)
+}
+
+/// Parse the options of a sequential value up to its ender, e.g.
+/// `d '1d clk '1clk nset '1pre nclear '1clr .`.
+/// Each control may be prefixed with `n` to make it active low.
+fn parse_sequential(storage: Storage, lexer: &mut logos::Lexer<'_, Token>) -> Result<Sequential, CirnoError> {
+  let clock_name = match storage {
+    Storage::Latch => "en",
+    _ => "clk",
+  };
+  let mut sequential = Sequential {
+    storage,
+    clock: Control { label: String::new(), active_low: false },
+    d: vec![],
+    j: vec![],
+    k: vec![],
+    set: None,
+    clear: None,
+    load: None,
+    data: None,
+  };
+  loop {
+    let option = match lexer.next() {
+      Some(Ok(Token::Ender)) => break,
+      Some(Ok(Token::Keyword)) => lexer.slice().to_string(),
+      Some(Ok(u)) => return Err(CirnoError::UnexpectedToken(Token::Keyword, u)),
+      Some(Err(_e)) => return Err(CirnoError::UnrecognizedToken(lexer.slice().to_string())),
+      None => return Err(CirnoError::OutOfTokens(Token::Ender)),
+    };
+    let label = expect_token!(lexer, Token::Identifier)?;
+    let (name, active_low) = match option.strip_prefix('n') {
+      Some(name) if [clock_name, "set", "clear", "load"].contains(&name) => (name, true),
+      _ => (option.as_str(), false),
+    };
+    let control = Control { label: label.clone(), active_low };
+    match name {
+      n if n == clock_name => sequential.clock = control,
+      "d" if storage != Storage::Jk => sequential.d.push(label),
+      "j" if storage == Storage::Jk => sequential.j.push(label),
+      "k" if storage == Storage::Jk => sequential.k.push(label),
+      "set" => sequential.set = Some(control),
+      "clear" => sequential.clear = Some(control),
+      "load" if storage != Storage::Latch => sequential.load = Some(control),
+      "data" if storage != Storage::Latch => sequential.data = Some(label),
+      _ => return Err(CirnoError::InvalidSequentialOption(option)),
+    }
+  }
+  if sequential.clock.label.is_empty() {
+    return Err(CirnoError::MissingAttribute(clock_name.to_string()))
+  }
+  if sequential.load.is_some() != sequential.data.is_some() {
+    return Err(CirnoError::MissingAttribute("load and data".to_string()))
+  }
+  Ok(sequential)
+}
+
+/// Parse a table up to its ender, e.g. `'a 'b hl h lh h else l .`.
+/// The labels of the inputs come first, then each row as a pattern of `h`, `l` or `x`
+/// (either) for each input, followed by the output for it.
+fn parse_table(lexer: &mut logos::Lexer<'_, Token>) -> Result<Table, CirnoError> {
+  let mut table = Table { inputs: vec![], rows: vec![], otherwise: None };
+  loop {
+    let pattern = match lexer.next() {
+      Some(Ok(Token::Ender)) => break,
+      Some(Ok(Token::Identifier)) if table.rows.is_empty() => {
+        table.inputs.push(lexer.slice().to_string());
+        continue;
+      },
+      Some(Ok(Token::Keyword)) => lexer.slice().to_string(),
+      Some(Ok(u)) => return Err(CirnoError::UnexpectedToken(Token::Keyword, u)),
+      Some(Err(_e)) => return Err(CirnoError::UnrecognizedToken(lexer.slice().to_string())),
+      None => return Err(CirnoError::OutOfTokens(Token::Ender)),
+    };
+    let output = expect_token!(lexer, Token::Keyword)?;
+    let Some(output) = parse_level(&output) else {
+      return Err(CirnoError::InvalidTableRow(format!("{} {}", pattern, output)))
+    };
+    if pattern == "else" {
+      table.otherwise = Some(output);
+      continue;
+    }
+    let levels: Option<Vec<Option<Voltage>>> = pattern
+      .chars()
+      .map(|c| match c {
+        'x' => Some(None),
+        c => parse_level(&c.to_string()).map(Some),
+      })
+      .collect();
+    match levels {
+      Some(levels) if levels.len() == table.inputs.len() => table.rows.push(Row { levels, output }),
+      _ => return Err(CirnoError::InvalidTableRow(pattern)),
     }
-    values.push(lexer.slice().to_string());
   }
-  Ok(values)
+  if table.inputs.is_empty() {
+    return Err(CirnoError::MissingAttribute("table inputs".to_string()))
+  }
+  Ok(table)
+}
+
+/// Parse a level in a table: `h`, `l`, or `x` for unknown.
+fn parse_level(s: &str) -> Option<Voltage> {
+  match s {
+    "h" => Some(Voltage::High),
+    "l" => Some(Voltage::Low),
+    "x" => Some(Voltage::Unknown),
+    _ => None,
+  }
 }
 
 fn object_default(token: &str) -> Result<ObjectEnum, CirnoError> {
   match token {
     "chip" => Ok(ObjectEnum::Chip(Chip::default())),
+    "header" => Ok(ObjectEnum::Header(Header::default())),
     "meta" => Ok(ObjectEnum::Meta(Meta::default())),
     "net" => Ok(ObjectEnum::Net(Net::default())),
     "pin" => Ok(ObjectEnum::Pin(Pin::default())),
@@ -189,7 +453,7 @@ fn object_default(token: &str) -> Result<ObjectEnum, CirnoError> {
   }
 }
 
-fn parse_object(token: &str, lexer: &mut logos::Lexer<'_, Token>) -> Result<ObjectEnum, anyhow::Error> {
+fn parse_object(token: &str, lexer: &mut logos::Lexer<'_, Token>) -> Result<ObjectEnum, CirnoError> {
   // create uninitialized object
   let mut object = object_default(token)?;
   // parse tokens into attributes
@@ -200,3 +464,110 @@ fn parse_object(token: &str, lexer: &mut logos::Lexer<'_, Token>) -> Result<Obje
   // return the object
   Ok(object)
 }
+
+#[cfg(test)]
+mod tests {
+  use crate::{CirnoState, diagnostics::Diagnostics, error::CirnoError, project::{ObjectEnum, Package, Pin, Value, Voltage}};
+  use std::collections::HashMap;
+  use super::parse;
+
+  fn parse_pin(contents: &str, diagnostics: &mut Diagnostics) -> Option<Pin> {
+    match parse(contents, "test.cic", diagnostics).pop() {
+      Some(ObjectEnum::Pin(pin)) => Some(pin),
+      _ => None,
+    }
+  }
+
+  #[test]
+  fn nested_expression() {
+    let mut diagnostics = Diagnostics::default();
+    let mut pin = parse_pin(": pin label 'y dir out value nor (and 'a 'b) (and 'c (not 'd)) .", &mut diagnostics).unwrap();
+    assert!(!diagnostics.has_errors(), "{:?}", diagnostics.items);
+    assert!(matches!(pin.value, Value::Expression(_)));
+    let mut voltage = |levels: [bool; 4]| {
+      let voltages: HashMap<String, Voltage> = ["'a", "'b", "'c", "'d"]
+        .iter()
+        .zip(levels)
+        .map(|(label, high)| (label.to_string(), if high { Voltage::High } else { Voltage::Low }))
+        .collect();
+      pin.calculate_voltage_from_value(&voltages).unwrap()
+    };
+    assert_eq!(voltage([false, false, false, false]), Voltage::High);
+    assert_eq!(voltage([true, true, false, false]), Voltage::Low);
+    assert_eq!(voltage([false, false, true, false]), Voltage::Low);
+    assert_eq!(voltage([false, false, true, true]), Voltage::High);
+  }
+
+  #[test]
+  fn flat_gate_keeps_its_form() {
+    let mut diagnostics = Diagnostics::default();
+    let pin = parse_pin(": pin label 'y dir out value nand 'a 'b .", &mut diagnostics).unwrap();
+    assert!(matches!(pin.value, Value::Nand(labels) if labels == ["'a", "'b"]));
+  }
+
+  #[test]
+  fn unbalanced_parentheses() {
+    for contents in [
+      ": pin label 'y dir out value nor (and 'a 'b .",
+      ": pin label 'y dir out value nor (and 'a 'b",
+      ": pin label 'y dir out value and 'a 'b ) .",
+      ": pin label 'y dir out value not (not 'a",
+    ] {
+      let mut diagnostics = Diagnostics::default();
+      parse_pin(contents, &mut diagnostics);
+      assert!(diagnostics.has_errors(), "{}", contents);
+    }
+  }
+
+  // the errors from laying out a chip definition named `test`
+  fn layout(contents: &str) -> Vec<String> {
+    let mut diagnostics = Diagnostics::default();
+    let mut objects = parse(contents, "test.cic", &mut diagnostics);
+    assert!(!diagnostics.has_errors(), "{:?}", diagnostics.items);
+    let valid = crate::layout_pins(&mut objects, "test", &CirnoState::with_size(0, 0), &mut diagnostics).unwrap();
+    assert_eq!(valid, !diagnostics.has_errors());
+    diagnostics.items.into_iter().map(|x| x.message).collect()
+  }
+
+  #[test]
+  fn layout_valid() {
+    assert!(layout(": header name test package dip4\n: pin num 2 label 'a\n: pin num 1 label 'b\n: pin num 3\n: pin num 4").is_empty());
+    assert!(layout(": header name test\n: pin label 'a\n: pin label 'b").is_empty());
+  }
+
+  #[test]
+  fn layout_bad_name() {
+    let errors = layout(": header name other\n: pin\n: pin");
+    assert_eq!(errors, [CirnoError::HeaderNameMismatch("other".to_string(), "test".to_string()).to_string()]);
+  }
+
+  #[test]
+  fn layout_misplaced_header() {
+    let errors = layout(": pin\n: header name test\n: pin");
+    assert_eq!(errors, [CirnoError::MisplacedHeader.to_string()]);
+  }
+
+  #[test]
+  fn layout_package_pin_count() {
+    let errors = layout(": header name test package dip14\n: pin\n: pin");
+    assert_eq!(errors, [CirnoError::PackageMismatch(Package::Dip(14), 2).to_string()]);
+  }
+
+  #[test]
+  fn layout_numbers_all_or_none() {
+    let errors = layout(": header name test\n: pin num 1\n: pin");
+    assert_eq!(errors, [CirnoError::MissingPinNumber.to_string()]);
+  }
+
+  #[test]
+  fn layout_duplicate_numbers() {
+    let errors = layout(": header name test\n: pin num 1\n: pin num 1");
+    assert_eq!(errors, [CirnoError::DuplicatePinNumber(1).to_string()]);
+  }
+
+  #[test]
+  fn layout_numbers_out_of_range() {
+    let errors = layout(": header name test\n: pin num 0\n: pin num 3");
+    assert_eq!(errors, [CirnoError::PinNumberOutOfRange(0, 2).to_string(), CirnoError::PinNumberOutOfRange(3, 2).to_string()]);
+  }
+}
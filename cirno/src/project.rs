@@ -1,12 +1,12 @@
-use crate::{error::CirnoError, terminal::{assert_is_within_bounds_unchecked, move_within_bounds, EventResult}, CirnoState};
+use crate::{diagnostics::Diagnostics, error::CirnoError, terminal::{assert_is_within_bounds_unchecked, move_within_bounds, EventResult}, short_chip_type, CirnoState};
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::io::stdout;
-use std::ops::{Add, Sub};
+use std::ops::{Add, Range, Sub};
 use crossterm::{execute, event::{KeyCode, KeyModifiers}, style::{Color, Colors}};
 use enum_dispatch::enum_dispatch;
 
-#[derive(Clone, Copy, Default, PartialEq)]
+#[derive(Clone, Copy, Default, Eq, Hash, PartialEq)]
 pub struct Vector2 {
   pub x: u16,
   pub y: u16,
@@ -63,6 +63,40 @@ impl Region {
   }
 }
 
+#[derive(Clone, Debug, Default, PartialEq)]
+// the place in a file that an object or error came from
+pub struct Span {
+  pub file: String,
+  pub line: usize,
+  pub columns: Range<usize>,
+}
+
+impl Span {
+  pub fn new(file: &str, line: usize, columns: Range<usize>) -> Span {
+    Span { file: file.to_string(), line, columns }
+  }
+}
+
+impl std::fmt::Display for Span {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+    // objects added while editing have not been written to a file yet
+    if self.file.is_empty() {
+      return f.write_str("<unsaved>")
+    }
+    // columns are stored 0-indexed, but displayed 1-indexed
+    write!(f, "{}:{}:{}", self.file, self.line, self.columns.start + 1)
+  }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+// the comments and blank lines around an object in a file, kept verbatim so that
+// they can be written back out
+pub struct Comments {
+  pub before: Vec<String>,
+  pub trailing: Option<String>,
+  pub after: Vec<String>,
+}
+
 #[derive(Clone, Default)]
 // a value that a pin can have
 pub enum Value {
@@ -75,10 +109,264 @@ pub enum Value {
   Nor(Vec<String>),
   Not(String),
   Or(Vec<String>),
-  Xor(String, String),
+  Sequential(Box<Sequential>),
+  Table(Box<Table>),
+  Xor(Vec<String>),
+  // anything nested, which has no flat form above
+  Expression(Expression),
   Vcc,
 }
 
+impl Value {
+  /// Return the labels that this value depends on.
+  pub fn inputs(&self) -> Vec<String> {
+    match self {
+      Value::And(labels) | Value::Nand(labels) | Value::Nor(labels) | Value::Or(labels) | Value::Xor(labels) => labels.clone(),
+      Value::Not(label) => vec![label.clone()],
+      Value::Sequential(sequential) => sequential.inputs(),
+      Value::Table(table) => table.inputs.clone(),
+      Value::Expression(expression) => expression.labels(),
+      _ => vec![],
+    }
+  }
+  /// Return this value with every label that it depends on replaced using `f`.
+  pub fn map_labels<F: Fn(String) -> String>(self, f: F) -> Value {
+    let all = |labels: Vec<String>| labels.into_iter().map(&f).collect();
+    match self {
+      Value::And(labels) => Value::And(all(labels)),
+      Value::Nand(labels) => Value::Nand(all(labels)),
+      Value::Nor(labels) => Value::Nor(all(labels)),
+      Value::Not(label) => Value::Not(f(label)),
+      Value::Or(labels) => Value::Or(all(labels)),
+      Value::Sequential(s) => {
+        let control = |c: Control| Control { label: f(c.label), active_low: c.active_low };
+        Value::Sequential(Box::new(Sequential {
+          storage: s.storage,
+          clock: control(s.clock),
+          d: all(s.d),
+          j: all(s.j),
+          k: all(s.k),
+          set: s.set.map(control),
+          clear: s.clear.map(control),
+          load: s.load.map(control),
+          data: s.data.map(&f),
+        }))
+      },
+      Value::Table(table) => Value::Table(Box::new(Table { inputs: all(table.inputs), ..*table })),
+      Value::Xor(labels) => Value::Xor(all(labels)),
+      Value::Expression(expression) => Value::Expression(expression.map_labels(&f)),
+      v => v,
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
+// a nested boolean expression over the levels of labels
+// nand and nor are written as the NOT of an AND or OR
+pub enum Expression {
+  And(Vec<Expression>),
+  Label(String),
+  Not(Box<Expression>),
+  Or(Vec<Expression>),
+  Xor(Vec<Expression>),
+}
+
+impl Expression {
+  /// Return every label in this expression, in the order they first appear.
+  pub fn labels(&self) -> Vec<String> {
+    let mut labels: Vec<String> = vec![];
+    self.collect_labels(&mut labels);
+    labels
+  }
+  fn collect_labels(&self, labels: &mut Vec<String>) {
+    match self {
+      Expression::And(operands) | Expression::Or(operands) | Expression::Xor(operands) => {
+        for operand in operands.iter() {
+          operand.collect_labels(labels);
+        }
+      },
+      Expression::Label(label) => {
+        if !labels.contains(label) {
+          labels.push(label.clone());
+        }
+      },
+      Expression::Not(operand) => operand.collect_labels(labels),
+    }
+  }
+  /// Return this expression with every label replaced using `f`.
+  pub fn map_labels<F: Fn(String) -> String>(self, f: &F) -> Expression {
+    let all = |operands: Vec<Expression>| operands.into_iter().map(|x| x.map_labels(f)).collect();
+    match self {
+      Expression::And(operands) => Expression::And(all(operands)),
+      Expression::Label(label) => Expression::Label(f(label)),
+      Expression::Not(operand) => Expression::Not(Box::new(operand.map_labels(f))),
+      Expression::Or(operands) => Expression::Or(all(operands)),
+      Expression::Xor(operands) => Expression::Xor(all(operands)),
+    }
+  }
+  /// Return the level of this expression, given the level of each label from `input`.
+  pub fn evaluate(&self, input: &dyn Fn(&str) -> Voltage) -> Voltage {
+    let all = |operands: &Vec<Expression>| operands.iter().map(|x| x.evaluate(input)).collect::<Vec<Voltage>>();
+    match self {
+      Expression::And(operands) => all(operands).iter().fold(Voltage::High, |acc, v| acc.and(v)),
+      Expression::Label(label) => input(label),
+      Expression::Not(operand) => operand.evaluate(input).not(),
+      Expression::Or(operands) => all(operands).iter().fold(Voltage::Low, |acc, v| acc.or(v)),
+      Expression::Xor(operands) => all(operands).iter().fold(Voltage::Low, |acc, v| acc.xor(v)),
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
+// a row of a table, matching when each input is at its level, or at any level for None
+pub struct Row {
+  pub levels: Vec<Option<Voltage>>,
+  pub output: Voltage,
+}
+
+#[derive(Clone, Debug)]
+// a value looked up from the levels of its inputs, e.g. for a decoder or multiplexer
+// the first row that matches decides the output, or `otherwise` if none of them do
+pub struct Table {
+  pub inputs: Vec<String>,
+  pub rows: Vec<Row>,
+  pub otherwise: Option<Voltage>,
+}
+
+impl Table {
+  /// Return the output for the given levels of the inputs, in the same order.
+  /// An unknown input that could change which row matches makes the output unknown,
+  /// unless every row it could match gives the same output.
+  pub fn lookup(&self, levels: &[Voltage]) -> Voltage {
+    let mut outputs: Vec<&Voltage> = vec![];
+    let mut matched = false;
+    for row in self.rows.iter() {
+      let matches = row.levels
+        .iter()
+        .zip(levels)
+        .fold(Voltage::High, |acc, (expected, level)| match expected {
+          Some(expected) => acc.and(&level.xor(expected).not()),
+          None => acc,
+        });
+      match matches {
+        Voltage::Low => continue,
+        Voltage::High => {
+          outputs.push(&row.output);
+          matched = true;
+          break;
+        },
+        _ => outputs.push(&row.output),
+      }
+    }
+    if !matched {
+      outputs.push(self.otherwise.as_ref().unwrap_or(&Voltage::Unknown));
+    }
+    match outputs.iter().all(|x| *x == outputs[0]) {
+      true => outputs[0].clone(),
+      false => Voltage::Unknown,
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
+// an input of a sequential value that is either asserted or not
+pub struct Control {
+  pub label: String,
+  // asserted when low, or for a clock, triggered on the falling edge
+  pub active_low: bool,
+}
+
+impl Control {
+  /// Return High if this control is asserted at the given level, Low if it is not, and
+  /// Unknown if that cannot be known.
+  pub fn asserted(&self, level: &Voltage) -> Voltage {
+    match self.active_low {
+      true => level.not(),
+      false => level.and(&Voltage::High),
+    }
+  }
+}
+
+#[derive(Clone, Debug, Default)]
+// how a pin drives the net it is on
+pub enum Output {
+  // always drives its level
+  #[default]
+  Push,
+  // only ever pulls low, so that outputs sharing a net form a wired-AND
+  OpenCollector,
+  // drives its level while enabled, and nothing otherwise
+  TriState(Control),
+}
+
+impl Output {
+  /// Return this output with the label of its enable replaced using `f`.
+  pub fn map_labels<F: Fn(String) -> String>(self, f: F) -> Output {
+    match self {
+      Output::TriState(c) => Output::TriState(Control { label: f(c.label), active_low: c.active_low }),
+      o => o,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+// how a sequential value stores its level
+pub enum Storage {
+  // an edge-triggered D flip-flop
+  D,
+  // an edge-triggered JK flip-flop
+  Jk,
+  // a transparent latch
+  Latch,
+}
+
+#[derive(Clone, Debug)]
+// a value that remembers its level between changes to its inputs
+// `d`, `j` and `k` are each the AND of every label given for them
+pub struct Sequential {
+  pub storage: Storage,
+  // the clock of a flip-flop, or the enable of a latch
+  pub clock: Control,
+  pub d: Vec<String>,
+  pub j: Vec<String>,
+  pub k: Vec<String>,
+  // asynchronous set and clear, which override everything else
+  pub set: Option<Control>,
+  pub clear: Option<Control>,
+  // synchronous load of `data`, which overrides `d`, `j` and `k`
+  pub load: Option<Control>,
+  pub data: Option<String>,
+}
+
+impl Sequential {
+  /// Return every label that this value depends on.
+  pub fn inputs(&self) -> Vec<String> {
+    let controls = [Some(&self.clock), self.set.as_ref(), self.clear.as_ref(), self.load.as_ref()];
+    controls
+      .into_iter()
+      .flatten()
+      .map(|c| c.label.clone())
+      .chain(self.d.iter().chain(self.j.iter()).chain(self.k.iter()).cloned())
+      .chain(self.data.clone())
+      .collect()
+  }
+}
+
+#[derive(Clone, Debug)]
+// what the simulator remembers about a sequential value between simulations
+pub struct SequentialState {
+  pub q: Voltage,
+  // the level of the clock when it was last seen
+  pub clock: Option<Voltage>,
+}
+
+impl Default for SequentialState {
+  fn default() -> SequentialState {
+    // flip-flops and latches power up in a level that cannot be known
+    SequentialState { q: Voltage::Unknown, clock: None }
+  }
+}
+
 impl Debug for Value {
   fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
     match self {
@@ -110,11 +398,24 @@ impl Debug for Value {
         values.fmt(f)?;
         f.write_str("\u{1b}[33m)\u{1b}[0m")?;
       },
-      Value::Xor(a, b) => {
+      Value::Sequential(sequential) => {
+        f.write_fmt(format_args!("\u{1b}[33m{:?}(\u{1b}[0m", sequential.storage))?;
+        sequential.inputs().fmt(f)?;
+        f.write_str("\u{1b}[33m)\u{1b}[0m")?;
+      },
+      Value::Table(table) => {
+        f.write_str("\u{1b}[33mTable(\u{1b}[0m")?;
+        table.inputs.fmt(f)?;
+        f.write_str("\u{1b}[33m)\u{1b}[0m")?;
+      },
+      Value::Xor(values) => {
         f.write_str("\u{1b}[33mXor(\u{1b}[0m")?;
-        a.fmt(f)?;
-        f.write_str(", ")?;
-        b.fmt(f)?;
+        values.fmt(f)?;
+        f.write_str("\u{1b}[33m)\u{1b}[0m")?;
+      },
+      Value::Expression(expression) => {
+        f.write_str("\u{1b}[33mExpression(\u{1b}[0m")?;
+        expression.labels().fmt(f)?;
         f.write_str("\u{1b}[33m)\u{1b}[0m")?;
       },
       Value::Vcc => {
@@ -131,12 +432,171 @@ impl Debug for Value {
   }
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, PartialEq)]
+// the logic level of a pin or net
 pub enum Voltage {
+  // not driven by anything
   #[default]
   Floating,
   High,
   Low,
+  // driven, but to a level that cannot be known, e.g. by a gate with an unknown input
+  Unknown,
+}
+
+impl Voltage {
+  /// Return the logical AND of two levels: low if either is low, high if both are high,
+  /// and unknown otherwise.
+  pub fn and(&self, other: &Voltage) -> Voltage {
+    match (self, other) {
+      (Voltage::Low, _) | (_, Voltage::Low) => Voltage::Low,
+      (Voltage::High, Voltage::High) => Voltage::High,
+      _ => Voltage::Unknown,
+    }
+  }
+  /// Return the logical OR of two levels: high if either is high, low if both are low,
+  /// and unknown otherwise.
+  pub fn or(&self, other: &Voltage) -> Voltage {
+    match (self, other) {
+      (Voltage::High, _) | (_, Voltage::High) => Voltage::High,
+      (Voltage::Low, Voltage::Low) => Voltage::Low,
+      _ => Voltage::Unknown,
+    }
+  }
+  /// Return the logical XOR of two levels, which is unknown unless both are known.
+  pub fn xor(&self, other: &Voltage) -> Voltage {
+    match (self, other) {
+      (Voltage::High, Voltage::Low) | (Voltage::Low, Voltage::High) => Voltage::High,
+      (Voltage::High, Voltage::High) | (Voltage::Low, Voltage::Low) => Voltage::Low,
+      _ => Voltage::Unknown,
+    }
+  }
+  /// Return the logical NOT of a level, which is unknown unless it is known.
+  pub fn not(&self) -> Voltage {
+    match self {
+      Voltage::High => Voltage::Low,
+      Voltage::Low => Voltage::High,
+      _ => Voltage::Unknown,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+// which way a pin of a chip faces, as far as the rest of the board is concerned
+pub enum Direction {
+  Input,
+  Output,
+  Power,
+  Nc,
+}
+
+impl Direction {
+  /// Return the direction named by a keyword in a chip definition, e.g. `in`.
+  pub fn from_keyword(keyword: &str) -> Option<Direction> {
+    match keyword {
+      "in" => Some(Direction::Input),
+      "out" => Some(Direction::Output),
+      "power" => Some(Direction::Power),
+      "nc" => Some(Direction::Nc),
+      _ => None,
+    }
+  }
+  /// Return the keyword for a direction, as written in a chip definition.
+  pub fn keyword(&self) -> &'static str {
+    match self {
+      Direction::Input => "in",
+      Direction::Output => "out",
+      Direction::Power => "power",
+      Direction::Nc => "nc",
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+// the physical package of a chip
+pub enum Package {
+  // a dual in-line package with the given number of pins
+  Dip(u16),
+}
+
+impl Package {
+  /// Return the package named by a keyword in a chip definition, e.g. `dip14`.
+  pub fn from_keyword(keyword: &str) -> Option<Package> {
+    let pins: u16 = keyword.strip_prefix("dip")?.parse().ok()?;
+    match pins > 0 && pins.is_multiple_of(2) {
+      true => Some(Package::Dip(pins)),
+      false => None,
+    }
+  }
+  /// Return the keyword for a package, as written in a chip definition.
+  pub fn keyword(&self) -> String {
+    match self {
+      Package::Dip(pins) => format!("dip{}", pins),
+    }
+  }
+  /// Return the number of pins on a package.
+  pub fn pins(&self) -> usize {
+    match self {
+      Package::Dip(pins) => usize::from(*pins),
+    }
+  }
+}
+
+impl std::fmt::Display for Package {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+    match self {
+      Package::Dip(pins) => write!(f, "DIP-{}", pins),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+// a logic family, which decides how the inputs of its chips behave
+pub enum Family {
+  // 74LS low-power Schottky TTL
+  Ls,
+  #[default]
+  Unknown,
+}
+
+impl Family {
+  /// Return the family named by a keyword in a chip definition, e.g. `ls`.
+  pub fn from_keyword(keyword: &str) -> Option<Family> {
+    match keyword {
+      "ls" => Some(Family::Ls),
+      _ => None,
+    }
+  }
+  /// Return the keyword for a family, as written in a chip definition, if it has one.
+  pub fn keyword(&self) -> Option<&'static str> {
+    match self {
+      Family::Ls => Some("ls"),
+      Family::Unknown => None,
+    }
+  }
+  /// Return the family of a chip type, from its part number, e.g. `ls0xx/sn74ls08`.
+  pub fn from_chip_type(t: &str) -> Family {
+    match t.rsplit('/').next() {
+      Some(name) if name.contains("74ls") => Family::Ls,
+      _ => Family::Unknown,
+    }
+  }
+  /// Return the level that an unconnected input reads as.
+  /// TTL inputs are pulled up internally, so an unconnected LS input floats high;
+  /// for any other family, the level is unknown.
+  pub fn floating_input(&self) -> Voltage {
+    match self {
+      Family::Ls => Voltage::High,
+      Family::Unknown => Voltage::Unknown,
+    }
+  }
+  /// Return the number of inputs of the same family that one output can drive, if known.
+  pub fn fan_out(&self) -> Option<usize> {
+    match self {
+      Family::Ls => Some(20),
+      Family::Unknown => None,
+    }
+  }
 }
 
 impl Debug for Voltage {
@@ -145,6 +605,7 @@ impl Debug for Voltage {
       Voltage::High => f.write_str("\u{1b}[31mHigh\u{1b}[0m")?,
       Voltage::Low => f.write_str("\u{1b}[34mLow\u{1b}[0m")?,
       Voltage::Floating => f.write_str("Floating")?,
+      Voltage::Unknown => f.write_str("\u{1b}[33mUnknown\u{1b}[0m")?,
     };
     Ok(())
   }
@@ -155,10 +616,19 @@ impl Debug for Voltage {
 pub enum Attribute {
   Bounds(Vector2),
   Color(Color),
+  Datasheet(String),
+  Description(String),
+  Direction(Direction),
+  Family(Family),
   From(Vector2),
+  Gap(u16),
   Label(String),
+  Name(String),
   Num(u16),
+  Output(Output),
+  Package(Package),
   Position(Vector2),
+  Strip(u16),
   To(Vector2),
   Type(String),
   Value(Value),
@@ -175,6 +645,7 @@ impl std::fmt::Display for Attribute {
 #[enum_dispatch]
 pub enum ObjectEnum {
   Chip(Chip),
+  Header(Header),
   Meta(Meta),
   Net(Net),
   Pin(Pin),
@@ -186,6 +657,7 @@ impl Debug for ObjectEnum {
     f.write_str("ObjectEnum::")?;
     match self {
       ObjectEnum::Chip(chip) => chip.fmt(f),
+      ObjectEnum::Header(header) => header.fmt(f),
       ObjectEnum::Meta(meta) => meta.fmt(f),
       ObjectEnum::Net(net) => net.fmt(f),
       ObjectEnum::Pin(pin) => pin.fmt(f),
@@ -198,10 +670,14 @@ impl Debug for ObjectEnum {
 pub trait Object: Debug {
   // fn new() -> Self;
   fn apply_attribute(&mut self, attribute: Attribute) -> Result<(), CirnoError>;
+  fn get_span(&self) -> &Span;
+  fn set_span(&mut self, span: Span);
+  fn get_comments(&self) -> &Comments;
+  fn set_comments(&mut self, comments: Comments);
   fn get_region(&self) -> Option<&Region>;
   fn set_region_size(&mut self, state: &CirnoState) -> Result<(), anyhow::Error>;
   fn get_char(&self, position: Vector2) -> Option<(char, Color)>;
-  fn verify(&self, state: &CirnoState) -> Result<(), CirnoError>;
+  fn verify(&self, state: &CirnoState, diagnostics: &mut Diagnostics);
   fn render(&self, colors: Colors, state: &CirnoState) -> Result<(), anyhow::Error>;
   fn report(&self, state: &CirnoState) -> Result<(String, Color), anyhow::Error>;
   fn highlight(&self, state: &CirnoState) -> Result<(), anyhow::Error>;
@@ -210,8 +686,12 @@ pub trait Object: Debug {
 #[derive(Clone, Debug, Default)]
 pub struct Chip {
   pub t: String,
+  // the reference designator, e.g. `u3`, which is numbered automatically if not given
+  pub name: String,
   pub region: Region,
   pub pins: Vec<Pin>,
+  pub span: Span,
+  pub comments: Comments,
 }
 
 impl Object for Chip {
@@ -219,10 +699,23 @@ impl Object for Chip {
     match attribute {
       Attribute::Type(t) => self.t = t,
       Attribute::Position(vec2) => self.region.position = vec2,
+      Attribute::Name(name) => self.name = name,
       a => return Err(CirnoError::InvalidAttributeForObject(a, "chip".to_string())),
     }
     Ok(())
   }
+  fn get_span(&self) -> &Span {
+    &self.span
+  }
+  fn set_span(&mut self, span: Span) {
+    self.span = span;
+  }
+  fn get_comments(&self) -> &Comments {
+    &self.comments
+  }
+  fn set_comments(&mut self, comments: Comments) {
+    self.comments = comments;
+  }
   fn get_region(&self) -> Option<&Region> {
     Some(&self.region)
   }
@@ -238,17 +731,114 @@ impl Object for Chip {
       _ => Some((' ', Color::White)),
     }
   }
-  fn verify(&self, state: &CirnoState) -> Result<(), CirnoError> {
+  fn verify(&self, state: &CirnoState, diagnostics: &mut Diagnostics) {
     // chip type
-    if self.t.eq("") {
-      return Err(CirnoError::MissingAttribute("chip type".to_string()))
+    if self.t.is_empty() {
+      diagnostics.error(&self.span, CirnoError::MissingAttribute("chip type".to_string()));
     }
     // TODO: chip position
     // bounds check
-    assert_is_within_bounds_unchecked(self.region.position.x, self.region.position.y, state)?;
-    assert_is_within_bounds_unchecked(self.region.position.x + self.region.size.x - 1, self.region.position.y + self.region.size.y - 1, state)?;
+    let bounds = assert_is_within_bounds_unchecked(self.region.position.x, self.region.position.y, state)
+      .and_then(|_| assert_is_within_bounds_unchecked(self.region.position.x + self.region.size.x - 1, self.region.position.y + self.region.size.y - 1, state));
+    diagnostics.check(&self.span, bounds);
+    // the body of the chip must sit over the gap, with a row of pins on each side
+    if let Some(gap) = state.meta.gap {
+      if self.region.position.y + self.region.size.y / 2 != gap {
+        diagnostics.error(&self.span, CirnoError::ChipNotStraddlingGap(gap));
+      }
+    }
+  }
+  fn render(&self, _colors: Colors, _state: &CirnoState) -> Result<(), anyhow::Error> {
+    Ok(())
+  }
+  fn report(&self, state: &CirnoState) -> Result<(String, Color), anyhow::Error> {
+    let mut report = format!("{} ({})", self.name.to_uppercase(), short_chip_type(self.t.clone()));
+    if let Some(header) = state.cic_headers.get(&self.t).filter(|x| !x.description.is_empty()) {
+      report.push_str(&format!(": {}", header.description));
+    }
+    Ok((report, Color::White))
+  }
+  fn highlight(&self, state: &CirnoState) -> Result<(), anyhow::Error> {
+    self.render_pins(Colors::new(Color::Black, Color::Yellow), state)
+  }
+}
+
+impl Chip {
+  /// Draw the pins that this chip was converted to, which are drawn in its place.
+  pub fn render_pins(&self, colors: Colors, state: &CirnoState) -> Result<(), anyhow::Error> {
+    if self.name.is_empty() {
+      return Ok(())
+    }
+    for object in state.objects.borrow().iter() {
+      match object {
+        ObjectEnum::Pin(pin) if pin.chip == self.name => pin.render(colors, state)?,
+        _ => {},
+      }
+    }
+    Ok(())
+  }
+}
+
+#[derive(Clone, Debug, Default)]
+// the first line of a chip definition, describing the part that it defines
+pub struct Header {
+  pub name: String,
+  pub description: String,
+  pub package: Option<Package>,
+  pub family: Family,
+  pub datasheet: String,
+  pub span: Span,
+  pub comments: Comments,
+}
+
+impl Header {
+  /// Return the header assumed for a chip definition that has none, from its chip type.
+  pub fn infer(t: &str) -> Header {
+    Header {
+      name: t.rsplit('/').next().unwrap_or(t).to_string(),
+      family: Family::from_chip_type(t),
+      ..Default::default()
+    }
+  }
+}
+
+impl Object for Header {
+  fn apply_attribute(&mut self, attribute: Attribute) -> Result<(), CirnoError> {
+    match attribute {
+      Attribute::Datasheet(url) => self.datasheet = url,
+      Attribute::Description(description) => self.description = description,
+      Attribute::Family(family) => self.family = family,
+      Attribute::Name(name) => self.name = name,
+      Attribute::Package(package) => self.package = Some(package),
+      a => return Err(CirnoError::InvalidAttributeForObject(a, "header".to_string())),
+    }
+    Ok(())
+  }
+  fn get_span(&self) -> &Span {
+    &self.span
+  }
+  fn set_span(&mut self, span: Span) {
+    self.span = span;
+  }
+  fn get_comments(&self) -> &Comments {
+    &self.comments
+  }
+  fn set_comments(&mut self, comments: Comments) {
+    self.comments = comments;
+  }
+  fn get_region(&self) -> Option<&Region> {
+    None
+  }
+  fn set_region_size(&mut self, _state: &CirnoState) -> Result<(), anyhow::Error> {
     Ok(())
   }
+  fn get_char(&self, _position: Vector2) -> Option<(char, Color)> {
+    None
+  }
+  fn verify(&self, _state: &CirnoState, diagnostics: &mut Diagnostics) {
+    // headers are only read from chip definitions, which are not verified as projects
+    diagnostics.error(&self.span, CirnoError::HeaderOutsideChip);
+  }
   fn render(&self, _colors: Colors, _state: &CirnoState) -> Result<(), anyhow::Error> {
     Ok(())
   }
@@ -263,16 +853,57 @@ impl Object for Chip {
 #[derive(Clone, Debug, Default)]
 pub struct Meta {
   pub bounds: Vector2,
+  // the row of the center channel, if the board has terminal strips
+  pub gap: Option<u16>,
+  // the number of connected holes in each terminal strip
+  pub strip: u16,
+  pub span: Span,
+  pub comments: Comments,
+}
+
+impl Meta {
+  /// Return the terminal strip that a position is in: the holes in the same column
+  /// on the same side of the gap that are connected to each other.
+  /// Without a gap, or outside of the strips, a position is only connected to itself.
+  pub fn strip_at(&self, position: Vector2) -> Region {
+    let hole = Region { position, size: Vector2 { x: 1, y: 1 } };
+    let Some(gap) = self.gap else { return hole };
+    let height = self.strip.max(1);
+    // strips are cut short by the edges of the board, in case it has not been verified
+    let top = gap.saturating_sub(height);
+    let bottom = gap.saturating_add(height).min(self.bounds.y.saturating_sub(1));
+    if position.y < gap && position.y >= top {
+      Region { position: Vector2 { x: position.x, y: top }, size: Vector2 { x: 1, y: gap - top } }
+    } else if position.y > gap && position.y <= bottom {
+      Region { position: Vector2 { x: position.x, y: gap + 1 }, size: Vector2 { x: 1, y: bottom - gap } }
+    } else {
+      hole
+    }
+  }
 }
 
 impl Object for Meta {
   fn apply_attribute(&mut self, attribute: Attribute) -> Result<(), CirnoError> {
     match attribute {
       Attribute::Bounds(vec2) => self.bounds = vec2,
+      Attribute::Gap(y) => self.gap = Some(y),
+      Attribute::Strip(height) => self.strip = height,
       a => return Err(CirnoError::InvalidAttributeForObject(a, "meta".to_string())),
     }
     Ok(())
   }
+  fn get_span(&self) -> &Span {
+    &self.span
+  }
+  fn set_span(&mut self, span: Span) {
+    self.span = span;
+  }
+  fn get_comments(&self) -> &Comments {
+    &self.comments
+  }
+  fn set_comments(&mut self, comments: Comments) {
+    self.comments = comments;
+  }
   fn get_region(&self) -> Option<&Region> {
     None
   }
@@ -282,15 +913,23 @@ impl Object for Meta {
   fn get_char(&self, _position: Vector2) -> Option<(char, Color)> {
     None
   }
-  fn verify(&self, _state: &CirnoState) -> Result<(), CirnoError> {
+  fn verify(&self, _state: &CirnoState, diagnostics: &mut Diagnostics) {
     // bounds
     if self.bounds.x == 0 && self.bounds.y == 0 {
-      return Err(CirnoError::MissingAttribute("bounds".to_string()))
+      diagnostics.error(&self.span, CirnoError::MissingAttribute("bounds".to_string()));
+    } else if self.bounds.x == 0 || self.bounds.y == 0 {
+      diagnostics.error(&self.span, CirnoError::NamelessInvalidValueForAttribute("bounds".to_string()));
     }
-    if self.bounds.x == 0 || self.bounds.y == 0 {
-      return Err(CirnoError::NamelessInvalidValueForAttribute("bounds".to_string()))
+    // terminal strips
+    match self.gap {
+      Some(gap) if gap < self.strip || gap + self.strip >= self.bounds.y => {
+        diagnostics.error(&self.span, CirnoError::StripsOutOfBounds(gap, self.strip));
+      },
+      None if self.strip != 0 => {
+        diagnostics.error(&self.span, CirnoError::MissingAttribute("gap".to_string()));
+      },
+      _ => {},
     }
-    Ok(())
   }
   fn render(&self, _colors: Colors, state: &CirnoState) -> Result<(), anyhow::Error> {
     let bound_x = state.meta.bounds.x;
@@ -330,6 +969,8 @@ impl Object for Meta {
 pub struct Net {
   pub t: String,
   pub region: Region,
+  pub span: Span,
+  pub comments: Comments,
 }
 
 impl Object for Net {
@@ -341,6 +982,18 @@ impl Object for Net {
     }
     Ok(())
   }
+  fn get_span(&self) -> &Span {
+    &self.span
+  }
+  fn set_span(&mut self, span: Span) {
+    self.span = span;
+  }
+  fn get_comments(&self) -> &Comments {
+    &self.comments
+  }
+  fn set_comments(&mut self, comments: Comments) {
+    self.comments = comments;
+  }
   fn get_region(&self) -> Option<&Region> {
     Some(&self.region)
   }
@@ -355,34 +1008,34 @@ impl Object for Net {
       _ => unreachable!(),
     }
   }
-  fn verify(&self, state: &CirnoState) -> Result<(), CirnoError> {
+  fn verify(&self, state: &CirnoState, diagnostics: &mut Diagnostics) {
     // net type
     match self.t.as_str() {
       "vcc" | "gnd" => {},
-      "" => return Err(CirnoError::MissingAttribute("net type".to_string())),
-      t => return Err(CirnoError::InvalidValueForAttribute(t.to_string(), "net type".to_string())),
+      "" => diagnostics.error(&self.span, CirnoError::MissingAttribute("net type".to_string())),
+      t => diagnostics.error(&self.span, CirnoError::InvalidValueForAttribute(t.to_string(), "net type".to_string())),
     };
-    // TODO: net y
+    // net y
+    if let Some(gap) = state.meta.gap {
+      let (y, height) = (self.region.position.y, state.meta.strip.max(1));
+      if y + height >= gap && y <= gap + height {
+        diagnostics.error(&self.span, CirnoError::NetInTerminalStrip(y));
+      }
+    }
     // bounds check
-    assert_is_within_bounds_unchecked(0, self.region.position.y, state)?;
-    Ok(())
+    diagnostics.check(&self.span, assert_is_within_bounds_unchecked(0, self.region.position.y, state));
   }
-  fn render(&self, _colors: Colors, state: &CirnoState) -> Result<(), anyhow::Error> {
+  fn render(&self, colors: Colors, state: &CirnoState) -> Result<(), anyhow::Error> {
     let y = self.region.position.y;
     let bound_x = state.meta.bounds.x;
     // rendering
     move_within_bounds(0, y, state)?;
-    match self.t.as_str() {
-      "vcc" => {
-        execute!(stdout(), crossterm::style::SetForegroundColor(Color::Red))?;
-        execute!(stdout(), crossterm::style::Print("+".repeat(bound_x.into())))?;
-      },
-      "gnd" => {
-        execute!(stdout(), crossterm::style::SetForegroundColor(Color::Blue))?;
-        execute!(stdout(), crossterm::style::Print("-".repeat(bound_x.into())))?;
-      },
-      _t => unreachable!(),
+    let (c, color) = self.get_char(Vector2::default()).unwrap();
+    match colors {
+      Colors { foreground: None, background: None } => execute!(stdout(), crossterm::style::SetForegroundColor(color))?,
+      colors => execute!(stdout(), crossterm::style::SetColors(colors))?,
     }
+    execute!(stdout(), crossterm::style::Print(c.to_string().repeat(bound_x.into())))?;
     execute!(stdout(), crossterm::style::ResetColor)?;
     Ok(())
   }
@@ -393,9 +1046,8 @@ impl Object for Net {
       _ => unreachable!(),
     }
   }
-  fn highlight(&self, _state: &CirnoState) -> Result<(), anyhow::Error> {
-    // TODO
-    Ok(())
+  fn highlight(&self, state: &CirnoState) -> Result<(), anyhow::Error> {
+    self.render(Colors::new(Color::Black, Color::Yellow), state)
   }
 }
 
@@ -409,8 +1061,17 @@ impl Object for Net {
 pub struct Pin {
   pub label: String,
   pub value: Value,
+  pub output: Output,
+  // the direction and number given in the chip definition, if any
+  pub explicit_direction: Option<Direction>,
+  pub num: Option<u16>,
+  // the designator of the chip that this pin belongs to, once chips are converted
+  pub chip: String,
   pub region: Region,
   pub voltage: Voltage,
+  pub family: Family,
+  pub span: Span,
+  pub comments: Comments,
 }
 
 impl Pin {
@@ -425,65 +1086,139 @@ impl Pin {
     }
     Ok(())
   }
-  pub fn calculate_voltage_from_value(&mut self, voltages: &HashMap<String, Voltage>) -> Result<Voltage, anyhow::Error> {
-    match &self.value {
-      Value::And(labels) => {
-        for label in labels {
-          let v = voltages.get(label);
-          match v {
-            Some(&Voltage::High) => { continue },
-            _ => return Ok(Voltage::Low),
-          };
-        }
-        Ok(Voltage::High)
-      },
-      Value::Nand(labels) => {
-        for label in labels {
-          let v = voltages.get(label);
-          match v {
-            Some(&Voltage::High) => { continue },
-            _ => return Ok(Voltage::High), // TODO: is this still correct if some v is Floating?
-          };
-        }
-        Ok(Voltage::Low)
-      },
-      Value::Nor(labels) => {
-        for label in labels {
-          let v = voltages.get(label);
-          match v {
-            Some(&Voltage::Low) => { continue; },
-            _ => return Ok(Voltage::Low), // TODO: is this still correct if some v is Floating?
-          };
-        }
-        Ok(Voltage::High)
+  /// Return the label of this pin as written in its chip definition, without the
+  /// designator of its chip.
+  pub fn local_label(&self) -> &str {
+    self.label
+      .strip_prefix(&self.chip)
+      .and_then(|x| x.strip_prefix('.'))
+      .unwrap_or(&self.label)
+  }
+  /// Return a description of this pin for messages, e.g. `U3 pin 4 '2a`.
+  pub fn describe(&self) -> String {
+    let name = match self.value {
+      _ if !self.label.is_empty() => self.local_label().to_string(),
+      Value::Gnd => "gnd".to_string(),
+      Value::Vcc => "vcc".to_string(),
+      Value::Nc => "nc".to_string(),
+      _ => String::new(),
+    };
+    match (self.chip.as_str(), self.num) {
+      ("", _) => name,
+      (chip, Some(num)) => format!("{} pin {} {}", chip.to_uppercase(), num, name).trim_end().to_string(),
+      (chip, None) => format!("{} {}", chip.to_uppercase(), name),
+    }
+  }
+  /// Return which way this pin faces, as given in its chip definition.
+  /// Otherwise, rails are power, and a pin is an output if its value depends on anything.
+  pub fn direction(&self) -> Direction {
+    self.explicit_direction.unwrap_or_else(|| self.inferred_direction())
+  }
+  /// Return which way this pin faces, judging only by its value.
+  pub fn inferred_direction(&self) -> Direction {
+    match self.value {
+      Value::Vcc | Value::Gnd => Direction::Power,
+      Value::Nc => Direction::Nc,
+      _ if !self.value.inputs().is_empty() => Direction::Output,
+      _ => Direction::Input,
+    }
+  }
+  /// Return every label that this pin depends on, including the enable of its output.
+  pub fn inputs(&self) -> Vec<String> {
+    let mut inputs = self.value.inputs();
+    if let Output::TriState(enable) = &self.output {
+      if !inputs.contains(&enable.label) {
+        inputs.push(enable.label.clone());
+      }
+    }
+    inputs
+  }
+  /// Return what this pin drives onto its net when its value is at `level`, where
+  /// Floating means that it drives nothing.
+  pub fn drive(&self, level: Voltage, voltages: &HashMap<String, Voltage>) -> Voltage {
+    match &self.output {
+      Output::Push => level,
+      Output::OpenCollector => match level {
+        Voltage::High | Voltage::Floating => Voltage::Floating,
+        level => level,
       },
-      Value::Not(label) => {
-        match voltages.get(label) {
-          Some(&Voltage::Low) => Ok(Voltage::High),
-          _ => Ok(Voltage::Low),
-        }
+      Output::TriState(enable) => match enable.asserted(&self.input(voltages, &enable.label)) {
+        Voltage::High => level,
+        Voltage::Low => Voltage::Floating,
+        _ => Voltage::Unknown,
       },
-      Value::Or(labels) => {
-        for label in labels {
-          let v = voltages.get(label);
-          match v {
-            Some(&Voltage::High) => return Ok(Voltage::High),
-            _ => { continue },
-          };
-        }
-        Ok(Voltage::Low)
+    }
+  }
+  /// Return the level of an input of this pin, given the levels of every pin by label.
+  /// An input that is not driven reads as whatever the family of this pin decides.
+  fn input(&self, voltages: &HashMap<String, Voltage>, label: &str) -> Voltage {
+    match voltages.get(label) {
+      Some(Voltage::Floating) | None => self.family.floating_input(),
+      Some(v) => v.clone(),
+    }
+  }
+  pub fn calculate_voltage_from_value(&mut self, voltages: &HashMap<String, Voltage>) -> Result<Voltage, anyhow::Error> {
+    let inputs = |labels: &Vec<String>| -> Vec<Voltage> { labels.iter().map(|l| self.input(voltages, l)).collect() };
+    match &self.value {
+      Value::And(labels) => Ok(inputs(labels).iter().fold(Voltage::High, |acc, v| acc.and(v))),
+      Value::Nand(labels) => Ok(inputs(labels).iter().fold(Voltage::High, |acc, v| acc.and(v)).not()),
+      Value::Nor(labels) => Ok(inputs(labels).iter().fold(Voltage::Low, |acc, v| acc.or(v)).not()),
+      Value::Not(label) => Ok(self.input(voltages, label).not()),
+      Value::Or(labels) => Ok(inputs(labels).iter().fold(Voltage::Low, |acc, v| acc.or(v))),
+      Value::Xor(labels) => Ok(inputs(labels).iter().fold(Voltage::Low, |acc, v| acc.xor(v))),
+      Value::Expression(expression) => Ok(expression.evaluate(&|label| self.input(voltages, label))),
+      Value::Table(table) => Ok(table.lookup(&inputs(&table.inputs))),
+      // a sequential value depends on what it remembers, see calculate_voltage_with_state
+      Value::Sequential(_) => Ok(self.voltage.clone()),
+      // not a gate, so the level is whatever the net it is on has
+      Value::Gnd | Value::Nc | Value::None | Value::Vcc => Ok(self.voltage.clone()),
+    }
+  }
+  /// Calculate the voltage of a pin from its inputs and, for a sequential value, from what
+  /// it remembers in `state`, which is updated.
+  /// An unknown clock level never triggers a flip-flop.
+  pub fn calculate_voltage_with_state(&mut self, voltages: &HashMap<String, Voltage>, state: &mut SequentialState) -> Result<Voltage, anyhow::Error> {
+    let Value::Sequential(s) = &self.value else { return self.calculate_voltage_from_value(voltages) };
+    let all = |labels: &Vec<String>| labels.iter().fold(Voltage::High, |acc, l| acc.and(&self.input(voltages, l)));
+    let asserted = |c: &Option<Control>| match c {
+      Some(c) => c.asserted(&self.input(voltages, &c.label)),
+      None => Voltage::Low,
+    };
+    let clock = s.clock.asserted(&self.input(voltages, &s.clock.label));
+    let previous = state.clock.replace(clock.clone());
+    let next = match s.storage {
+      // a latch follows its input while it is enabled
+      Storage::Latch => match clock {
+        Voltage::High => all(&s.d),
+        Voltage::Low => state.q.clone(),
+        _ if all(&s.d) == state.q => state.q.clone(),
+        _ => Voltage::Unknown,
       },
-      Value::Xor(a, b) => {
-        let va = voltages.get(a);
-        let vb = voltages.get(b);
-        match (va, vb) {
-          (Some(&Voltage::High), Some(&Voltage::Low)) => Ok(Voltage::High),
-          (Some(&Voltage::Low), Some(&Voltage::High)) => Ok(Voltage::High),
-          _ => Ok(Voltage::Low),
+      // a flip-flop samples its inputs when its clock becomes asserted
+      Storage::D | Storage::Jk if previous == Some(Voltage::Low) && clock == Voltage::High => {
+        match asserted(&s.load) {
+          Voltage::High => self.input(voltages, s.data.as_deref().unwrap_or_default()),
+          Voltage::Low if s.storage == Storage::D => all(&s.d),
+          Voltage::Low => match (all(&s.j), all(&s.k)) {
+            (Voltage::Low, Voltage::Low) => state.q.clone(),
+            (Voltage::High, Voltage::Low) => Voltage::High,
+            (Voltage::Low, Voltage::High) => Voltage::Low,
+            (Voltage::High, Voltage::High) => state.q.not(),
+            _ => Voltage::Unknown,
+          },
+          _ => Voltage::Unknown,
         }
       },
-      _ => Ok(self.voltage.clone()), // TODO: eliminate
-    }
+      Storage::D | Storage::Jk => state.q.clone(),
+    };
+    // asynchronous set takes priority over clear
+    state.q = match (asserted(&s.set), asserted(&s.clear)) {
+      (Voltage::High, _) => Voltage::High,
+      (Voltage::Low, Voltage::High) => Voltage::Low,
+      (Voltage::Low, Voltage::Low) => next,
+      _ => Voltage::Unknown,
+    };
+    Ok(state.q.clone())
   }
 }
 
@@ -493,10 +1228,25 @@ impl Object for Pin {
       Attribute::Label(label) => self.label = label,
       // Attribute::Position(vec2) => self.region.position = vec2,
       Attribute::Value(value) => self.value = value,
+      Attribute::Output(output) => self.output = output,
+      Attribute::Direction(direction) => self.explicit_direction = Some(direction),
+      Attribute::Num(num) => self.num = Some(num),
       a => return Err(CirnoError::InvalidAttributeForObject(a, "pin".to_string())),
     }
     Ok(())
   }
+  fn get_span(&self) -> &Span {
+    &self.span
+  }
+  fn set_span(&mut self, span: Span) {
+    self.span = span;
+  }
+  fn get_comments(&self) -> &Comments {
+    &self.comments
+  }
+  fn set_comments(&mut self, comments: Comments) {
+    self.comments = comments;
+  }
   fn get_region(&self) -> Option<&Region> {
     Some(&self.region)
   }
@@ -507,25 +1257,30 @@ impl Object for Pin {
   fn get_char(&self, _position: Vector2) -> Option<(char, Color)> {
     Some(('.', Color::White))
   }
-  fn verify(&self, state: &CirnoState) -> Result<(), CirnoError> {
+  fn verify(&self, state: &CirnoState, diagnostics: &mut Diagnostics) {
     // TODO: pin position
     // bounds check
-    assert_is_within_bounds_unchecked(self.region.position.x, self.region.position.y, state)?;
-    Ok(())
+    diagnostics.check(&self.span, assert_is_within_bounds_unchecked(self.region.position.x, self.region.position.y, state));
   }
-  fn render(&self, _colors: Colors, state: &CirnoState) -> Result<(), anyhow::Error> {
+  fn render(&self, colors: Colors, state: &CirnoState) -> Result<(), anyhow::Error> {
     let x = self.region.position.x;
     let y = self.region.position.y;
     // rendering
     move_within_bounds(x, y, state)?;
+    if let Colors { foreground: None, background: None } = colors {
+      execute!(stdout(), crossterm::style::Print("."))?;
+      return Ok(())
+    }
+    execute!(stdout(), crossterm::style::SetColors(colors))?;
     execute!(stdout(), crossterm::style::Print("."))?;
+    execute!(stdout(), crossterm::style::ResetColor)?;
     Ok(())
   }
-  fn report(&self, _state: &CirnoState) -> Result<(String, Color), anyhow::Error> {
-    let (report, color);
+  fn report(&self, state: &CirnoState) -> Result<(String, Color), anyhow::Error> {
+    let (mut report, color);
     // basic value
-    if self.label.ne("") {
-      (report, color) = (self.label.to_string(), Color::Cyan);
+    if !self.label.is_empty() {
+      (report, color) = (self.local_label().to_string(), Color::Cyan);
     } else {
       (report, color) = match self.value {
         Value::Gnd => ("gnd".to_string(), Color::Blue),
@@ -534,16 +1289,24 @@ impl Object for Pin {
         _ => (String::new(), Color::White),
       };
     }
+    // the chip and pin number, e.g. `U3 (sn74ls08) pin 4 '2a`
+    let chip = state.objects.borrow().iter().find_map(|x| match x {
+      ObjectEnum::Chip(chip) if !self.chip.is_empty() && chip.name == self.chip => Some(chip.t.clone()),
+      _ => None,
+    });
+    if let (Some(t), Some(num)) = (chip, self.num) {
+      report = format!("{} ({}) pin {} {}", self.chip.to_uppercase(), short_chip_type(t), num, report);
+    }
     // voltage
     match self.voltage {
       Voltage::High => Ok((format!("{} ({})", report, "hi"), color)),
       Voltage::Low => Ok((format!("{} ({})", report, "lo"), color)),
+      Voltage::Unknown => Ok((format!("{} ({})", report, "x"), color)),
       Voltage::Floating => Ok((report, color)),
     }
   }
-  fn highlight(&self, _state: &CirnoState) -> Result<(), anyhow::Error> {
-    // TODO
-    Ok(())
+  fn highlight(&self, state: &CirnoState) -> Result<(), anyhow::Error> {
+    self.render(Colors::new(Color::Black, Color::Yellow), state)
   }
 }
 
@@ -553,6 +1316,8 @@ pub struct Wire {
   pub from: Vector2,
   pub to: Vector2,
   pub label: char,
+  pub span: Span,
+  pub comments: Comments,
 }
 
 impl Wire {
@@ -568,6 +1333,8 @@ impl Default for Wire {
       from: Vector2::default(),
       to: Vector2::default(),
       label: 'a',
+      span: Span::default(),
+      comments: Comments::default(),
     }
   }
 }
@@ -582,6 +1349,18 @@ impl Object for Wire {
     }
     Ok(())
   }
+  fn get_span(&self) -> &Span {
+    &self.span
+  }
+  fn set_span(&mut self, span: Span) {
+    self.span = span;
+  }
+  fn get_comments(&self) -> &Comments {
+    &self.comments
+  }
+  fn set_comments(&mut self, comments: Comments) {
+    self.comments = comments;
+  }
   fn get_region(&self) -> Option<&Region> {
     None
   }
@@ -591,15 +1370,15 @@ impl Object for Wire {
   fn get_char(&self, _position: Vector2) -> Option<(char, Color)> {
     Some((self.label, self.color))
   }
-  fn verify(&self, state: &CirnoState) -> Result<(), CirnoError> {
+  fn verify(&self, state: &CirnoState, diagnostics: &mut Diagnostics) {
     // from and to
     if self.from == self.to {
-      return Err(CirnoError::InvalidWire)
+      diagnostics.error(&self.span, CirnoError::InvalidWire);
     }
     // bounds check
-    assert_is_within_bounds_unchecked(self.from.x, self.from.y, state)?;
-    assert_is_within_bounds_unchecked(self.to.x, self.to.y, state)?;
-    Ok(())
+    let bounds = assert_is_within_bounds_unchecked(self.from.x, self.from.y, state)
+      .and_then(|_| assert_is_within_bounds_unchecked(self.to.x, self.to.y, state));
+    diagnostics.check(&self.span, bounds);
   }
   fn render(&self, colors: Colors, state: &CirnoState) -> Result<(), anyhow::Error> {
     let (from_x, from_y) = (self.from.x, self.from.y);
@@ -624,12 +1403,15 @@ impl Object for Wire {
   }
 }
 
+/// A function called when a key is pressed in a given mode.
+pub type KeyCommand = fn(&mut CirnoState) -> Result<EventResult, anyhow::Error>;
+
 #[derive(Clone)]
 pub struct Mode {
   pub mode_set_cb: fn(&mut CirnoState) -> Result<(), anyhow::Error>,
   pub key_event_cb: fn(KeyCode, KeyModifiers, &mut CirnoState) -> Result<EventResult, anyhow::Error>,
   pub resize_event_cb: fn(&mut CirnoState) -> Result<EventResult, anyhow::Error>,
-  pub key_commands: HashMap<char, fn(&mut CirnoState) -> Result<EventResult, anyhow::Error>>,
+  pub key_commands: HashMap<char, KeyCommand>,
   // TODO: state needed or not?
   // pub commands: HashMap<String, fn(&mut CirnoState) -> Result<EventResult, anyhow::Error>>,
   // pub arg_commands: HashMap<String, fn(Vec<&str>, &mut CirnoState) -> Result<EventResult, anyhow::Error>>,
@@ -637,7 +1419,50 @@ pub struct Mode {
 
 #[derive(Clone, Copy, Debug)]
 pub enum Modes {
+  Chip,
   Console,
   Empty,
+  Insert,
+  List,
+  Move,
   Normal,
+  Wire,
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Meta, Vector2};
+
+  // the top and height of the strip that a position is in
+  fn strip_at(meta: &Meta, y: u16) -> (u16, u16) {
+    let region = meta.strip_at(Vector2 { x: 1, y });
+    assert_eq!((region.position.x, region.size.x), (1, 1));
+    (region.position.y, region.size.y)
+  }
+
+  #[test]
+  fn strip_at_gap() {
+    let meta = Meta { bounds: Vector2 { x: 10, y: 12 }, gap: Some(5), strip: 3, ..Default::default() };
+    assert_eq!(strip_at(&meta, 1), (1, 1));
+    assert_eq!(strip_at(&meta, 2), (2, 3));
+    assert_eq!(strip_at(&meta, 4), (2, 3));
+    assert_eq!(strip_at(&meta, 5), (5, 1));
+    assert_eq!(strip_at(&meta, 6), (6, 3));
+    assert_eq!(strip_at(&meta, 8), (6, 3));
+    assert_eq!(strip_at(&meta, 9), (9, 1));
+  }
+
+  #[test]
+  fn strip_at_edges() {
+    // taller than the space above the gap
+    let meta = Meta { bounds: Vector2 { x: 10, y: 12 }, gap: Some(2), strip: 5, ..Default::default() };
+    assert_eq!(strip_at(&meta, 0), (0, 2));
+    assert_eq!(strip_at(&meta, 1), (0, 2));
+    assert_eq!(strip_at(&meta, 7), (3, 5));
+    assert_eq!(strip_at(&meta, 8), (8, 1));
+    // and below it
+    let meta = Meta { gap: Some(9), ..meta };
+    assert_eq!(strip_at(&meta, 10), (10, 2));
+    assert_eq!(strip_at(&meta, 11), (10, 2));
+  }
 }
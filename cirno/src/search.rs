@@ -1,12 +1,13 @@
-use crate::{CirnoState, bar, cursor, error::CirnoError, project::{Object, ObjectEnum}, terminal::{EventResult, backspace, read_line}};
-use crossterm::style::{Color, Colors};
+use crate::{CirnoState, bar, color_from_char, cursor, error::CirnoError, modes::list::Line, project::{Modes, Object, ObjectEnum, Vector2, Voltage}, short_chip_type, terminal::{EventResult, backspace, read_line}};
+use crossterm::style::Colors;
+use regex::Regex;
 
 /// Read a search query entered via the bar.
 pub fn read_from_bar(state: &mut CirnoState) -> Result<EventResult, anyhow::Error> {
   bar::message("/".to_string(), state)?;
   let line = read_line()?;
   // remove the slash if the search comes back empty
-  if line.eq("") {
+  if line.is_empty() {
     backspace()?;
   } else {
     query(line, state)?;
@@ -28,6 +29,10 @@ pub fn query(line: String, state: &mut CirnoState) -> Result<(), anyhow::Error>
       }
       query_wire(color, label, state)?
     },
+    Some('p') => query_pin(&Pattern::parse(chars.as_str())?, state),
+    Some('c') => query_chip(&Pattern::parse(chars.as_str())?, state),
+    Some('v') => query_voltage(chars.as_str().trim(), state)?,
+    Some('n') => query_nets(&Pattern::parse(chars.as_str())?, state),
     Some(_) => return Err(CirnoError::InvalidSearch.into()),
     None => unreachable!(),
   };
@@ -35,36 +40,248 @@ pub fn query(line: String, state: &mut CirnoState) -> Result<(), anyhow::Error>
   if len == 0 {
     return Err(CirnoError::NoResultsFound.into())
   }
-  // update state
+  show(result, state)?;
+  bar::message(format!("{} results", len), state)?;
+  Ok(())
+}
+
+/// A pattern that names are matched against: a regex between slashes, e.g. `/^'[12]a/`,
+/// a glob if it has wildcards, e.g. `'*a`, or otherwise the exact name.
+enum Pattern {
+  Exact(String),
+  Regex(Regex),
+}
+
+impl Pattern {
+  fn parse(s: &str) -> Result<Pattern, CirnoError> {
+    let invalid = |_| CirnoError::InvalidPattern(s.to_string());
+    if s.is_empty() {
+      return Err(CirnoError::InvalidSearch)
+    }
+    if let Some(regex) = s.strip_prefix('/').and_then(|x| x.strip_suffix('/')) {
+      return Regex::new(regex).map(Pattern::Regex).map_err(invalid)
+    }
+    if !s.contains(['*', '?', '[']) {
+      return Ok(Pattern::Exact(s.to_string()))
+    }
+    // a glob matches the whole name
+    let mut regex = "^".to_string();
+    for c in s.chars() {
+      match c {
+        '*' => regex.push_str(".*"),
+        '?' => regex.push('.'),
+        '[' | ']' => regex.push(c),
+        c => regex.push_str(&regex::escape(&c.to_string())),
+      }
+    }
+    regex.push('$');
+    Regex::new(&regex).map(Pattern::Regex).map_err(invalid)
+  }
+  fn matches(&self, name: &str) -> bool {
+    match self {
+      Pattern::Exact(s) => s == name,
+      Pattern::Regex(regex) => regex.is_match(name),
+    }
+  }
+}
+
+/// Highlight everything on the same net as the cursor: pins, wires and rails.
+pub fn query_net(state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  clear(state)?;
+  let Some(id) = state.netlist.net_at(state.cursor) else {
+    return Err(CirnoError::NoObjectAtCursor("highlight".to_string()).into())
+  };
+  let result = on_nets(&[id], state);
+  let count = |f: fn(&ObjectEnum) -> bool| result.iter().filter(|x| f(x)).count();
+  let pins = count(|x| matches!(x, ObjectEnum::Pin(_)));
+  let wires = count(|x| matches!(x, ObjectEnum::Wire(_)));
+  let mut message = format!("net {}: {} pins, {} wires", id, pins, wires);
+  if let Some(t) = state.netlist.rail(id) {
+    message.push_str(&format!(", {} rail", t));
+  }
+  show(result, state)?;
+  bar::message(message, state)?;
+  Ok(())
+}
+
+/// Set `state.search_result` and highlight every object in it.
+fn show(mut result: Vec<ObjectEnum>, state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  result.sort_by_key(|x| {
+    let position = position(x);
+    (position.y, position.x)
+  });
   state.search_result.replace(result);
+  highlight(state)?;
+  cursor::render(state)?;
+  Ok(())
+}
+
+/// Highlight every object in `state.search_result`.
+pub fn highlight(state: &CirnoState) -> Result<(), anyhow::Error> {
   for object in state.search_result.borrow().iter() {
     object.highlight(state)?;
   }
+  Ok(())
+}
+
+/// Return where the cursor goes to reach a search result.
+pub fn position(object: &ObjectEnum) -> Vector2 {
+  match object {
+    ObjectEnum::Wire(wire) => wire.from,
+    _ => object.get_region().map(|x| x.position).unwrap_or_default(),
+  }
+}
+
+/// Return each search result as a line of text with its position, under a heading.
+pub fn lines(state: &CirnoState) -> Result<Vec<Line>, anyhow::Error> {
+  let results = state.search_result.borrow();
+  let mut lines = vec![(format!("results ({})", results.len()), None)];
+  for (i, object) in results.iter().enumerate() {
+    let position = position(object);
+    let (report, color) = object.report(state)?;
+    lines.push((format!("  ({}, {}) {}", position.x, position.y, report), Some((i, color))));
+  }
+  Ok(lines)
+}
+
+/// Go back to the board from the list of results, moving the cursor to the one picked
+/// and keeping them all highlighted.
+pub fn close(selected: Option<usize>, state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  state.set_mode(Modes::Normal)?;
+  match selected {
+    Some(index) => jump(index, state),
+    None => {
+      highlight(state)?;
+      cursor::render(state)
+    },
+  }
+}
+
+/// Move the cursor to the next search result after it in reading order, or the previous
+/// one before it, wrapping around at either end.
+pub fn step(forward: bool, state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  let positions: Vec<(u16, u16)> = state.search_result
+    .borrow()
+    .iter()
+    .map(|x| {
+      let position = position(x);
+      (position.y, position.x)
+    })
+    .collect();
+  if positions.is_empty() {
+    return Err(CirnoError::NoResultsFound.into())
+  }
+  let cursor = (state.cursor.y, state.cursor.x);
+  let index = if forward {
+    positions.iter().position(|x| *x > cursor).unwrap_or(0)
+  } else {
+    positions.iter().rposition(|x| *x < cursor).unwrap_or(positions.len() - 1)
+  };
+  jump(index, state)
+}
+
+/// Move the cursor to the search result at `index`, keeping the results highlighted.
+/// The bar shows which result it is, e.g. `3/12`, and what it is.
+pub fn jump(index: usize, state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  let Some(object) = state.search_result.borrow().get(index).cloned() else {
+    return Err(CirnoError::NoResultsFound.into())
+  };
+  cursor::clear(state)?;
+  state.cursor = position(&object);
+  highlight(state)?;
   cursor::render(state)?;
-  bar::message(format!("{} results", len), state)?;
+  cursor::report(state)?;
+  let (report, _) = object.report(state)?;
+  let len = state.search_result.borrow().len();
+  bar::message(format!("{}/{}: {}", index + 1, len, report), state)?;
   Ok(())
 }
 
 /// Clear `state.search_result` and any associated highlighting.
+/// Conflicts stay highlighted, since they are only cleared by fixing them.
 pub fn clear(state: &mut CirnoState) -> Result<(), anyhow::Error> {
+  let colors = Colors { foreground: None, background: None };
   for object in state.search_result.borrow().iter() {
-    object.render(Colors { foreground: None, background: None }, state)?;
+    match object {
+      ObjectEnum::Chip(chip) => chip.render_pins(colors, state)?,
+      object => object.render(colors, state)?,
+    }
+  }
+  if !state.search_result.borrow().is_empty() {
+    state.render_conflicts()?;
   }
   state.search_result.replace(vec![]);
   Ok(())
 }
 
+/// Return everything on any of the given nets: pins, wires and rails.
+fn on_nets(ids: &[usize], state: &CirnoState) -> Vec<ObjectEnum> {
+  let on_net = |position: Vector2| state.netlist.net_at(position).is_some_and(|x| ids.contains(&x));
+  state.objects
+    .borrow()
+    .iter()
+    .filter(|x| match x {
+      ObjectEnum::Net(net) => on_net(Vector2 { x: 0, y: net.region.position.y }),
+      ObjectEnum::Pin(pin) => on_net(pin.region.position),
+      ObjectEnum::Wire(wire) => on_net(wire.from),
+      _ => false,
+    })
+    .cloned()
+    .collect()
+}
+
+/// Perform a pin search, matching either the label in its chip definition, e.g. `'2a`,
+/// or its label across the board, e.g. `u3.'2a`.
+fn query_pin(pattern: &Pattern, state: &CirnoState) -> Vec<ObjectEnum> {
+  state.objects
+    .borrow()
+    .iter()
+    .filter(|x| matches!(x, ObjectEnum::Pin(pin) if !pin.label.is_empty() && (pattern.matches(pin.local_label()) || pattern.matches(&pin.label))))
+    .cloned()
+    .collect()
+}
+
+/// Perform a chip search, matching either its part number, e.g. `sn74ls08`, or its
+/// full type, e.g. `ls0xx/sn74ls08`.
+fn query_chip(pattern: &Pattern, state: &CirnoState) -> Vec<ObjectEnum> {
+  state.objects
+    .borrow()
+    .iter()
+    .filter(|x| matches!(x, ObjectEnum::Chip(chip) if pattern.matches(&short_chip_type(chip.t.clone())) || pattern.matches(&chip.t)))
+    .cloned()
+    .collect()
+}
+
+/// Perform a search for pins at a simulated level: `hi`, `lo`, `x` or `z` for undriven.
+fn query_voltage(level: &str, state: &CirnoState) -> Result<Vec<ObjectEnum>, anyhow::Error> {
+  let voltage = match level {
+    "hi" => Voltage::High,
+    "lo" => Voltage::Low,
+    "x" => Voltage::Unknown,
+    "z" => Voltage::Floating,
+    _ => return Err(CirnoError::InvalidSearch.into()),
+  };
+  Ok(state.objects
+    .borrow()
+    .iter()
+    .filter(|x| matches!(x, ObjectEnum::Pin(pin) if pin.voltage == voltage))
+    .cloned()
+    .collect())
+}
+
+/// Perform a net search, matching either the number of a net, e.g. `12`, or the type of
+/// the rail on it, e.g. `vcc`.
+fn query_nets(pattern: &Pattern, state: &CirnoState) -> Vec<ObjectEnum> {
+  let ids: Vec<usize> = (0..state.netlist.members.len())
+    .filter(|id| pattern.matches(&id.to_string()) || state.netlist.rail(*id).is_some_and(|t| pattern.matches(t)))
+    .collect();
+  on_nets(&ids, state)
+}
+
 /// Perform a wire search, given a color and optional label.
 fn query_wire(color: Option<char>, label: Option<char>, state: &mut CirnoState) -> Result<Vec<ObjectEnum>, anyhow::Error> {
-  let color_struct = match color {
-    Some('r') => Color::Red,
-    Some('g') => Color::Green,
-    Some('y') => Color::Yellow,
-    Some('b') => Color::Blue,
-    Some('m') => Color::Magenta,
-    Some('c') => Color::Cyan,
-    Some(_) => return Err(CirnoError::InvalidSearch.into()),
-    None => return Err(CirnoError::InvalidSearch.into()),
+  let Some(color_struct) = color.and_then(color_from_char) else {
+    return Err(CirnoError::InvalidSearch.into())
   };
   let binding = state.objects.borrow();
   let wires = binding
@@ -87,3 +304,117 @@ fn query_wire(color: Option<char>, label: Option<char>, state: &mut CirnoState)
   }
   Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+  use crate::{CirnoState, diagnostics::Diagnostics, project::{ObjectEnum, Voltage}};
+  use super::{Pattern, query_chip, query_nets, query_pin, query_voltage};
+
+  const PROJECT: &str = "\
+: meta bounds 30 15
+: net type vcc y 0
+: net type gnd y 1
+: chip type ls0xx/sn74ls04 pos 0 2
+: chip name u3 type ls0xx/sn74ls08 pos 8 2
+: wire color red from 0 0 to 0 2
+: wire color blue from 6 1 to 6 4
+";
+
+  fn load() -> CirnoState {
+    let mut state = CirnoState::with_size(0, 0);
+    let mut diagnostics = Diagnostics::default();
+    crate::load(&mut state, PROJECT, "test.cip", &mut diagnostics).unwrap();
+    state.rebuild_simulation(&mut diagnostics).unwrap();
+    assert!(!diagnostics.has_errors(), "{:?}", diagnostics.items);
+    state
+  }
+
+  fn pattern(s: &str) -> Pattern {
+    Pattern::parse(s).unwrap()
+  }
+
+  // the label of each pin in a result, sorted
+  fn labels(result: &[ObjectEnum]) -> Vec<String> {
+    let mut labels: Vec<String> = result
+      .iter()
+      .filter_map(|x| match x {
+        ObjectEnum::Pin(pin) => Some(pin.label.clone()),
+        _ => None,
+      })
+      .collect();
+    labels.sort();
+    labels
+  }
+
+  #[test]
+  fn patterns() {
+    let exact = pattern("'1a");
+    assert!(exact.matches("'1a"));
+    assert!(!exact.matches("'1ab"));
+    let glob = pattern("'?a*");
+    assert!(glob.matches("'1a"));
+    assert!(glob.matches("'2ab"));
+    assert!(!glob.matches("u1.'1a"));
+    let class = pattern("'[12]y");
+    assert!(class.matches("'2y"));
+    assert!(!class.matches("'3y"));
+    // a regex is not anchored
+    let regex = pattern("/[12]a$/");
+    assert!(regex.matches("u1.'1a"));
+    assert!(!regex.matches("'3a"));
+  }
+
+  #[test]
+  fn bad_patterns() {
+    assert!(Pattern::parse("").is_err());
+    assert!(Pattern::parse("/(/").is_err());
+    assert!(Pattern::parse("'[1a").is_err());
+    // a bad query is an error before anything is searched
+    let mut state = load();
+    for line in ["p/(/", "c'[1a", "n", "vhigh", "q"] {
+      assert!(super::query(line.to_string(), &mut state).is_err(), "{}", line);
+    }
+  }
+
+  #[test]
+  fn pins() {
+    let state = load();
+    assert_eq!(labels(&query_pin(&pattern("'1a"), &state)), ["u1.'1a", "u3.'1a"]);
+    assert_eq!(labels(&query_pin(&pattern("u3.'1?"), &state)), ["u3.'1a", "u3.'1b", "u3.'1y"]);
+    assert_eq!(labels(&query_pin(&pattern("/^u1.'6/"), &state)), ["u1.'6a", "u1.'6y"]);
+    assert!(query_pin(&pattern("'9a"), &state).is_empty());
+  }
+
+  #[test]
+  fn chips() {
+    let state = load();
+    assert_eq!(query_chip(&pattern("sn74ls0?"), &state).len(), 2);
+    let result = query_chip(&pattern("ls0xx/sn74ls08"), &state);
+    assert!(matches!(result.as_slice(), [ObjectEnum::Chip(chip)] if chip.name == "u3"));
+    assert!(query_chip(&pattern("sn74ls0"), &state).is_empty());
+  }
+
+  #[test]
+  fn voltages() {
+    let state = load();
+    for (level, voltage) in [("hi", Voltage::High), ("lo", Voltage::Low), ("z", Voltage::Floating)] {
+      let result = query_voltage(level, &state).unwrap();
+      assert!(!result.is_empty(), "{}", level);
+      assert!(result.iter().all(|x| matches!(x, ObjectEnum::Pin(pin) if pin.voltage == voltage)));
+    }
+    assert!(query_voltage("high", &state).is_err());
+  }
+
+  #[test]
+  fn nets() {
+    let state = load();
+    let vcc = query_nets(&pattern("vcc"), &state);
+    assert!(vcc.iter().any(|x| matches!(x, ObjectEnum::Net(net) if net.t == "vcc")));
+    assert!(vcc.iter().any(|x| matches!(x, ObjectEnum::Wire(_))));
+    // the power pin of the inverter, at the end of the red wire
+    assert!(vcc.iter().any(|x| matches!(x, ObjectEnum::Pin(pin) if pin.chip == "u1")));
+    assert!(!vcc.iter().any(|x| matches!(x, ObjectEnum::Net(net) if net.t == "gnd")));
+    let id = state.netlist.net_at(crate::project::Vector2 { x: 0, y: 0 }).unwrap();
+    assert_eq!(query_nets(&pattern(&id.to_string()), &state).len(), vcc.len());
+  }
+}
@@ -0,0 +1,270 @@
+use crate::{color_to_string, project::*};
+
+/// Serialize a list of objects back into cirno's `: object attr ...` syntax.
+/// Chips should not have been converted yet, so that they are written out as
+/// `: chip type ... pos ...` rather than as their expanded pins.
+pub fn serialize(objects: &[ObjectEnum]) -> String {
+  let mut contents = String::new();
+  for object in objects {
+    let comments = object.get_comments();
+    for line in comments.before.iter() {
+      contents.push_str(line);
+      contents.push('\n');
+    }
+    contents.push_str(&serialize_object(object));
+    if let Some(comment) = &comments.trailing {
+      contents.push(' ');
+      contents.push_str(comment);
+    }
+    contents.push('\n');
+    for line in comments.after.iter() {
+      contents.push_str(line);
+      contents.push('\n');
+    }
+  }
+  contents
+}
+
+/// Serialize a single object into a line, without its comments or the trailing newline.
+pub fn serialize_object(object: &ObjectEnum) -> String {
+  match object {
+    ObjectEnum::Chip(chip) => {
+      let pos = chip.region.position;
+      match chip.name.as_str() {
+        "" => format!(": chip type {} pos {} {}", chip.t, pos.x, pos.y),
+        name => format!(": chip name {} type {} pos {} {}", name, chip.t, pos.x, pos.y),
+      }
+    },
+    ObjectEnum::Header(header) => {
+      let mut line = format!(": header name {}", header.name);
+      if !header.description.is_empty() {
+        line.push_str(&format!(" desc \"{}\"", header.description));
+      }
+      if let Some(package) = header.package {
+        line.push_str(&format!(" package {}", package.keyword()));
+      }
+      if let Some(family) = header.family.keyword() {
+        line.push_str(&format!(" family {}", family));
+      }
+      if !header.datasheet.is_empty() {
+        line.push_str(&format!(" datasheet \"{}\"", header.datasheet));
+      }
+      line
+    },
+    ObjectEnum::Meta(meta) => {
+      let mut line = format!(": meta bounds {} {}", meta.bounds.x, meta.bounds.y);
+      if let Some(gap) = meta.gap {
+        line.push_str(&format!(" gap {}", gap));
+      }
+      if meta.strip != 0 {
+        line.push_str(&format!(" strip {}", meta.strip));
+      }
+      line
+    },
+    ObjectEnum::Net(net) => {
+      format!(": net type {} y {}", net.t, net.region.position.y)
+    },
+    ObjectEnum::Pin(pin) => {
+      let mut line = ": pin".to_string();
+      if let Some(num) = pin.num {
+        line.push_str(&format!(" num {}", num));
+      }
+      if !pin.label.is_empty() {
+        line.push_str(&format!(" label {}", pin.label));
+      }
+      if let Some(direction) = pin.explicit_direction {
+        line.push_str(&format!(" dir {}", direction.keyword()));
+      }
+      if !matches!(pin.value, Value::None) {
+        line.push_str(&format!(" value {}", serialize_value(&pin.value)));
+      }
+      match &pin.output {
+        Output::Push => {},
+        Output::OpenCollector => line.push_str(" oc"),
+        Output::TriState(enable) => line.push_str(&format!(" {}oe {}", if enable.active_low { "n" } else { "" }, enable.label)),
+      }
+      line
+    },
+    ObjectEnum::Wire(wire) => {
+      format!(
+        ": wire color {} from {} {} to {} {}",
+        color_to_string(wire.color),
+        wire.from.x,
+        wire.from.y,
+        wire.to.x,
+        wire.to.y,
+      )
+    },
+  }
+}
+
+/// Serialize a sequential value, e.g. `dff d '1d clk '1clk nclear '1clr .`.
+fn serialize_sequential(sequential: &Sequential) -> String {
+  let control = |name: &str, c: &Control| format!(" {}{} {}", if c.active_low { "n" } else { "" }, name, c.label);
+  let mut line = match sequential.storage {
+    Storage::D => "dff".to_string(),
+    Storage::Jk => "jkff".to_string(),
+    Storage::Latch => "latch".to_string(),
+  };
+  for (name, labels) in [("d", &sequential.d), ("j", &sequential.j), ("k", &sequential.k)] {
+    for label in labels.iter() {
+      line.push_str(&format!(" {} {}", name, label));
+    }
+  }
+  let clock = match sequential.storage {
+    Storage::Latch => "en",
+    _ => "clk",
+  };
+  line.push_str(&control(clock, &sequential.clock));
+  for (name, c) in [("set", &sequential.set), ("clear", &sequential.clear), ("load", &sequential.load)] {
+    if let Some(c) = c {
+      line.push_str(&control(name, c));
+    }
+  }
+  if let Some(data) = &sequential.data {
+    line.push_str(&format!(" data {}", data));
+  }
+  line.push_str(" .");
+  line
+}
+
+/// Serialize a table, e.g. `table 'a 'b hl h lh h else l .`.
+fn serialize_table(table: &Table) -> String {
+  let level = |v: Option<&Voltage>| match v {
+    Some(Voltage::High) => 'h',
+    Some(Voltage::Low) => 'l',
+    _ => 'x',
+  };
+  let mut line = format!("table {}", table.inputs.join(" "));
+  for row in table.rows.iter() {
+    let pattern: String = row.levels.iter().map(|x| level(x.as_ref())).collect();
+    line.push_str(&format!(" {} {}", pattern, level(Some(&row.output))));
+  }
+  if let Some(otherwise) = &table.otherwise {
+    line.push_str(&format!(" else {}", level(Some(otherwise))));
+  }
+  line.push_str(" .");
+  line
+}
+
+/// Return the name of the gate at the top of an expression, along with its operands.
+/// A lone label is written as an AND of itself.
+fn gate(expression: &Expression) -> (&'static str, Vec<&Expression>) {
+  match expression {
+    Expression::And(operands) => ("and", operands.iter().collect()),
+    Expression::Label(_) => ("and", vec![expression]),
+    Expression::Not(operand) => match operand.as_ref() {
+      Expression::And(operands) => ("nand", operands.iter().collect()),
+      Expression::Or(operands) => ("nor", operands.iter().collect()),
+      operand => ("not", vec![operand]),
+    },
+    Expression::Or(operands) => ("or", operands.iter().collect()),
+    Expression::Xor(operands) => ("xor", operands.iter().collect()),
+  }
+}
+
+/// Serialize an operand of an expression, e.g. `'1a` or `(and '1a '1b)`.
+fn serialize_operand(expression: &Expression) -> String {
+  if let Expression::Label(label) = expression {
+    return label.clone()
+  }
+  let (op, operands) = gate(expression);
+  format!("({} {})", op, operands.iter().map(|x| serialize_operand(x)).collect::<Vec<String>>().join(" "))
+}
+
+/// Serialize the value of a pin, in the form expected by the `value` attribute.
+pub fn serialize_value(value: &Value) -> String {
+  match value {
+    Value::And(labels) => format!("and {} .", labels.join(" ")),
+    Value::Gnd => "gnd".to_string(),
+    Value::Nand(labels) => format!("nand {} .", labels.join(" ")),
+    Value::Nc => "nc".to_string(),
+    Value::None => String::new(),
+    Value::Nor(labels) => format!("nor {} .", labels.join(" ")),
+    Value::Not(label) => format!("not {}", label),
+    Value::Or(labels) => format!("or {} .", labels.join(" ")),
+    Value::Sequential(sequential) => serialize_sequential(sequential),
+    Value::Table(table) => serialize_table(table),
+    // two operands are written without an ender, as they always used to be
+    Value::Xor(labels) if labels.len() == 2 => format!("xor {}", labels.join(" ")),
+    Value::Xor(labels) => format!("xor {} .", labels.join(" ")),
+    Value::Expression(expression) => {
+      let (op, operands) = gate(expression);
+      let ender = match (op, operands.len()) {
+        ("not", _) | ("xor", 2) => "",
+        _ => " .",
+      };
+      format!("{} {}{}", op, operands.iter().map(|x| serialize_operand(x)).collect::<Vec<String>>().join(" "), ender)
+    },
+    Value::Vcc => "vcc".to_string(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{CirnoState, diagnostics::Diagnostics, parser, project::{Object, ObjectEnum}};
+  use super::serialize;
+
+  const PROJECT: &str = "\
+: meta bounds 30 15
+
+# rails
+: net type vcc y 0
+: net type gnd y 1
+
+: chip type ls0xx/sn74ls04 pos 0 2
+: chip name u3 type ls0xx/sn74ls08 pos 8 2 # the and gates
+: wire color red from 0 0 to 0 2
+: wire color blue from 6 1 to 6 4
+";
+
+  fn parse(contents: &str) -> Vec<ObjectEnum> {
+    let mut diagnostics = Diagnostics::default();
+    let objects = parser::parse(contents, "test.cip", &mut diagnostics);
+    assert!(!diagnostics.has_errors(), "{:?}", diagnostics.items);
+    objects
+  }
+
+  #[test]
+  fn round_trip() {
+    let contents = serialize(&parse(PROJECT));
+    assert_eq!(contents, PROJECT);
+    assert_eq!(serialize(&parse(&contents)), contents);
+  }
+
+  #[test]
+  fn comments_survive() {
+    let contents = "\
+# a header comment
+#
+# with a blank comment line
+
+: meta bounds 30 15 # the size of the board
+: net type vcc y 0
+
+# indented comments and blank lines are kept as they were
+    # like this
+: wire color red from 0 0 to 0 2 # # in a comment
+# at the end of the file
+";
+    let objects = parse(contents);
+    let comments = objects[0].get_comments();
+    assert_eq!(comments.before.len(), 4);
+    assert_eq!(comments.trailing.as_deref(), Some("# the size of the board"));
+    assert_eq!(objects[2].get_comments().after, ["# at the end of the file"]);
+    assert_eq!(serialize(&objects), contents);
+  }
+
+  #[test]
+  fn chips_are_not_expanded() {
+    let mut state = CirnoState::with_size(0, 0);
+    let mut diagnostics = Diagnostics::default();
+    crate::load(&mut state, PROJECT, "test.cip", &mut diagnostics).unwrap();
+    state.rebuild_simulation(&mut diagnostics).unwrap();
+    assert!(!diagnostics.has_errors(), "{:?}", diagnostics.items);
+    assert!(state.objects.borrow().iter().any(|x| matches!(x, ObjectEnum::Pin(_))));
+    let contents = serialize(&state.source.borrow());
+    assert_eq!(contents, PROJECT);
+    assert!(!contents.contains(": pin"));
+  }
+}
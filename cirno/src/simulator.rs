@@ -0,0 +1,388 @@
+use crate::{netlist::Netlist, project::{Pin, SequentialState, Value, Voltage}};
+use std::collections::HashMap;
+
+// what is kept between the steps of settling
+struct Run {
+  // whether each pin is a sequential gate
+  sequential: Vec<bool>,
+  limit: usize,
+  changes: Vec<usize>,
+  oscillating: Vec<usize>,
+  // whether sequential gates are evaluated yet
+  clocked: bool,
+}
+
+/// An event-driven logic simulator over the nets of a board.
+/// Each pin is on a node: the net it is connected to, or a node of its own if it is
+/// not connected to anything. What each gate does is defined by
+/// `Pin::calculate_voltage_from_value`, and what it drives onto its node by `Pin::drive`.
+/// Every gate on a node drives it at once: outputs that are off drive nothing, and
+/// outputs that disagree leave it unknown.
+pub struct Simulator {
+  // the node of each pin
+  nodes: Vec<usize>,
+  // the voltage of each node
+  voltages: Vec<Voltage>,
+  // what each pin drives onto its node, or Floating for nothing
+  drives: Vec<Voltage>,
+  // the gates on each node
+  drivers: Vec<Vec<usize>>,
+  // whether each node is held by a rail
+  fixed: Vec<bool>,
+  // the labels that each pin depends on, with the node of each
+  inputs: Vec<Vec<(String, usize)>>,
+  // the pins whose value depends on each node
+  readers: Vec<Vec<usize>>,
+}
+
+impl Simulator {
+  /// Create a simulator for the given pins, with every node floating except the rails.
+  pub fn new(pins: &[&mut Pin], netlist: &Netlist) -> Simulator {
+    let mut count = netlist.members.len();
+    let nodes: Vec<usize> = pins
+      .iter()
+      .map(|pin| netlist.net_at(pin.region.position).unwrap_or_else(|| {
+        count += 1;
+        count - 1
+      }))
+      .collect();
+    let mut voltages = vec![Voltage::Floating; count];
+    let mut fixed = vec![false; count];
+    for id in 0..netlist.members.len() {
+      (voltages[id], fixed[id]) = match netlist.rail(id) {
+        Some("vcc") => (Voltage::High, true),
+        Some("gnd") => (Voltage::Low, true),
+        _ => (Voltage::Floating, false),
+      };
+    }
+    let labels: HashMap<&str, usize> = pins
+      .iter()
+      .enumerate()
+      .filter(|(_, pin)| !pin.label.is_empty())
+      .map(|(i, pin)| (pin.label.as_str(), i))
+      .collect();
+    let inputs: Vec<Vec<(String, usize)>> = pins
+      .iter()
+      .map(|pin| {
+        pin
+          .inputs()
+          .into_iter()
+          .filter_map(|label| labels.get(label.as_str()).map(|i| (label, nodes[*i])))
+          .collect()
+      })
+      .collect();
+    let mut readers: Vec<Vec<usize>> = vec![vec![]; count];
+    for (i, pin_inputs) in inputs.iter().enumerate() {
+      for (_, node) in pin_inputs.iter() {
+        if !readers[*node].contains(&i) {
+          readers[*node].push(i);
+        }
+      }
+    }
+    let mut drivers: Vec<Vec<usize>> = vec![vec![]; count];
+    for (i, pin) in pins.iter().enumerate() {
+      if !pin.inputs().is_empty() {
+        drivers[nodes[i]].push(i);
+      }
+    }
+    let drives = vec![Voltage::Floating; pins.len()];
+    Simulator { nodes, voltages, drives, drivers, fixed, inputs, readers }
+  }
+  /// Evaluate every gate, then re-evaluate the gates reading any node that changes, until
+  /// nothing changes.
+  /// Sequential gates are held at what they stored, given by their label in `states`, until
+  /// every other gate has settled around them, so that they only see the edges that are
+  /// really there. `states` is updated with what they store afterwards.
+  /// Returns the gates that kept changing after every other gate could have settled,
+  /// which are left as they were when they were given up on.
+  pub fn settle(&mut self, pins: &mut [&mut Pin], states: &mut HashMap<String, SequentialState>) -> Result<Vec<usize>, anyhow::Error> {
+    let sequential: Vec<bool> = pins.iter().map(|pin| matches!(pin.value, Value::Sequential(_))).collect();
+    let gates: Vec<usize> = (0..pins.len()).filter(|i| !pins[*i].inputs().is_empty()).collect();
+    for gate in gates.iter().filter(|gate| sequential[**gate]) {
+      self.drives[*gate] = states.entry(pins[*gate].label.clone()).or_default().q.clone();
+      self.resolve(self.nodes[*gate]);
+    }
+    let mut run = Run {
+      sequential,
+      // without feedback, a gate changes at most once for each gate before it
+      limit: gates.len() + 1,
+      changes: vec![0; pins.len()],
+      oscillating: vec![],
+      clocked: false,
+    };
+    let (clocked, combinational): (Vec<usize>, Vec<usize>) = gates.into_iter().partition(|gate| run.sequential[*gate]);
+    self.run(combinational, &mut run, pins, states)?;
+    run.clocked = true;
+    self.run(clocked, &mut run, pins, states)?;
+    Ok(run.oscillating)
+  }
+  /// Evaluate the pending gates in steps, each against the voltages from before the step,
+  /// so that gates sharing a clock all see the same edge.
+  fn run(&mut self, mut pending: Vec<usize>, run: &mut Run, pins: &mut [&mut Pin], states: &mut HashMap<String, SequentialState>) -> Result<(), anyhow::Error> {
+    while !pending.is_empty() {
+      let mut updates: Vec<(usize, Voltage)> = vec![];
+      for gate in pending.drain(..) {
+        let voltages: HashMap<String, Voltage> = self.inputs[gate]
+          .iter()
+          .map(|(label, node)| (label.clone(), self.voltages[*node].clone()))
+          .collect();
+        let level = match run.sequential[gate] {
+          true => {
+            let state = states.entry(pins[gate].label.clone()).or_default();
+            pins[gate].calculate_voltage_with_state(&voltages, state)?
+          },
+          false => pins[gate].calculate_voltage_from_value(&voltages)?,
+        };
+        updates.push((gate, pins[gate].drive(level, &voltages)));
+      }
+      let mut queued = vec![false; pins.len()];
+      for (gate, drive) in updates {
+        if self.drives[gate] == drive {
+          continue;
+        }
+        run.changes[gate] += 1;
+        if run.changes[gate] > run.limit {
+          if !run.oscillating.contains(&gate) {
+            run.oscillating.push(gate);
+          }
+          continue;
+        }
+        self.drives[gate] = drive;
+        let node = self.nodes[gate];
+        if !self.resolve(node) {
+          continue;
+        }
+        for reader in self.readers[node].iter() {
+          if !queued[*reader] && (run.clocked || !run.sequential[*reader]) {
+            queued[*reader] = true;
+            pending.push(*reader);
+          }
+        }
+      }
+    }
+    Ok(())
+  }
+  /// Set the voltage of a node from what every gate on it drives, returning whether it
+  /// changed.
+  /// A node held by a rail never changes.
+  fn resolve(&mut self, node: usize) -> bool {
+    if self.fixed[node] {
+      return false
+    }
+    let mut drives = self.drivers[node].iter().map(|x| &self.drives[*x]).filter(|x| **x != Voltage::Floating);
+    let voltage = match drives.next() {
+      None => Voltage::Floating,
+      Some(first) if drives.all(|x| x == first) => first.clone(),
+      Some(_) => Voltage::Unknown,
+    };
+    let changed = self.voltages[node] != voltage;
+    self.voltages[node] = voltage;
+    changed
+  }
+  /// Return each node whose drivers conflict, along with the gates driving it high and
+  /// those driving it low.
+  /// A node held by a rail conflicts with any gate driving it to the other level, and
+  /// these are returned alone.
+  pub fn conflicts(&self) -> Vec<(usize, Vec<usize>, Vec<usize>)> {
+    let mut conflicts: Vec<(usize, Vec<usize>, Vec<usize>)> = vec![];
+    for (node, gates) in self.drivers.iter().enumerate() {
+      let driving = |level: Voltage| gates.iter().filter(|x| self.drives[**x] == level).copied().collect::<Vec<usize>>();
+      let (high, low) = (driving(Voltage::High), driving(Voltage::Low));
+      let (high, low) = match (self.fixed[node], &self.voltages[node]) {
+        (true, Voltage::High) => (vec![], low),
+        (true, _) => (high, vec![]),
+        (false, _) if high.is_empty() || low.is_empty() => continue,
+        (false, _) => (high, low),
+      };
+      if !high.is_empty() || !low.is_empty() {
+        conflicts.push((node, high, low));
+      }
+    }
+    conflicts
+  }
+  /// Return the voltage of the node that a pin is on.
+  pub fn voltage(&self, pin: usize) -> Voltage {
+    self.voltages[self.nodes[pin]].clone()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{diagnostics::Diagnostics, netlist::Netlist, parser, project::{Family, Meta, ObjectEnum, Pin, Vector2, Voltage}};
+  use super::Simulator;
+  use std::collections::HashMap;
+
+  // the result of settling a board: the voltage of each pin, the gates that oscillate,
+  // and the nodes whose drivers conflict
+  struct Settled {
+    voltages: Vec<Voltage>,
+    oscillating: Vec<usize>,
+    conflicts: usize,
+  }
+
+  /// Settle a board made from `contents`, where each pin is placed at (i, 0) in the
+  /// order it is given, and wires and rails refer to those positions.
+  /// Every pin is of the given family.
+  fn settle(contents: &str, family: Family) -> Settled {
+    let mut diagnostics = Diagnostics::default();
+    let mut objects = parser::parse(contents, "test.cip", &mut diagnostics);
+    assert!(!diagnostics.has_errors(), "{:?}", diagnostics.items);
+    let mut x = 0;
+    for object in objects.iter_mut() {
+      if let ObjectEnum::Pin(pin) = object {
+        pin.region.position = Vector2 { x, y: 0 };
+        pin.family = family;
+        x += 1;
+      }
+    }
+    let meta = Meta { bounds: Vector2 { x: 8, y: 4 }, ..Default::default() };
+    let netlist = Netlist::build(&objects, &meta);
+    let mut pins: Vec<&mut Pin> = objects
+      .iter_mut()
+      .filter_map(|x| match x {
+        ObjectEnum::Pin(pin) => Some(pin),
+        _ => None,
+      })
+      .collect();
+    let mut simulator = Simulator::new(&pins, &netlist);
+    let oscillating = simulator.settle(&mut pins, &mut HashMap::new()).unwrap();
+    Settled {
+      voltages: (0..pins.len()).map(|i| simulator.voltage(i)).collect(),
+      oscillating,
+      conflicts: simulator.conflicts().len(),
+    }
+  }
+
+  #[test]
+  fn gate_chain_settles() {
+    // listed backwards, so that a single pass in order would not be enough
+    let settled = settle("\
+: net type vcc y 1
+: pin label 'd value not 'c
+: pin label 'c value not 'b
+: pin label 'b value not 'a
+: pin label 'a
+: wire from 3 0 to 3 1
+", Family::Unknown);
+    assert_eq!(settled.voltages, vec![Voltage::Low, Voltage::High, Voltage::Low, Voltage::High]);
+    assert!(settled.oscillating.is_empty());
+    assert_eq!(settled.conflicts, 0);
+  }
+
+  #[test]
+  fn ring_oscillates() {
+    // floating inputs read high, so the ring starts from known levels
+    let settled = settle("\
+: pin label 'a value not 'c
+: pin label 'b value not 'a
+: pin label 'c value not 'b
+", Family::Ls);
+    assert!(!settled.oscillating.is_empty());
+  }
+
+  #[test]
+  fn unknown_propagates() {
+    // 'x floats, and pins of an unknown family read that as unknown
+    let settled = settle("\
+: net type gnd y 1
+: net type vcc y 2
+: pin label 'x
+: pin label 'lo
+: pin label 'hi
+: pin label 'n value not 'x
+: pin label 'and value and 'n 'lo .
+: pin label 'or value or 'n 'lo .
+: pin label 'or1 value or 'n 'hi .
+: wire from 1 0 to 1 1
+: wire from 2 0 to 2 2
+", Family::Unknown);
+    assert_eq!(settled.voltages[3], Voltage::Unknown);
+    // a controlling input decides the output whatever the unknown one is
+    assert_eq!(settled.voltages[4], Voltage::Low);
+    assert_eq!(settled.voltages[5], Voltage::Unknown);
+    assert_eq!(settled.voltages[6], Voltage::High);
+  }
+
+  #[test]
+  fn table_looks_up_rows() {
+    let settled = settle("\
+: net type gnd y 1
+: net type vcc y 2
+: pin label 'x
+: pin label 'lo
+: pin label 'hi
+: pin label 'q1 value table 'hi 'x hx h lh l else l .
+: pin label 'q2 value table 'x 'lo hx h lh l else l .
+: pin label 'q3 value table 'lo 'lo hx h lh l else l .
+: wire from 1 0 to 1 1
+: wire from 2 0 to 2 2
+", Family::Unknown);
+    // the unknown input does not matter to the row that matches
+    assert_eq!(settled.voltages[3], Voltage::High);
+    // but here it decides between rows with different outputs
+    assert_eq!(settled.voltages[4], Voltage::Unknown);
+    assert_eq!(settled.voltages[5], Voltage::Low);
+  }
+
+  #[test]
+  fn rails_are_not_driven() {
+    // 'a would pull the vcc rail low, but the rail holds
+    let settled = settle("\
+: net type vcc y 1
+: pin label 'x
+: pin label 'a value not 'x
+: pin label 'b value not 'a
+: wire from 0 0 to 0 1
+: wire from 1 0 to 1 1
+", Family::Unknown);
+    assert_eq!(settled.voltages, vec![Voltage::High, Voltage::High, Voltage::Low]);
+  }
+
+  #[test]
+  fn open_collectors_form_wired_and() {
+    // 'a pulls the net low, while 'b lets go of it
+    let settled = settle("\
+: net type gnd y 1
+: net type vcc y 2
+: pin label 'x
+: pin label 'y
+: pin label 'a value not 'x oc
+: pin label 'b value not 'y oc
+: wire from 0 0 to 0 2
+: wire from 1 0 to 1 1
+: wire from 2 0 to 3 0
+", Family::Unknown);
+    assert_eq!(settled.voltages[2], Voltage::Low);
+    assert_eq!(settled.conflicts, 0);
+    // with both let go, nothing drives the net
+    let settled = settle("\
+: net type gnd y 1
+: pin label 'x
+: pin label 'y
+: pin label 'a value not 'x oc
+: pin label 'b value not 'y oc
+: wire from 0 0 to 0 1
+: wire from 1 0 to 1 1
+: wire from 2 0 to 3 0
+", Family::Unknown);
+    assert_eq!(settled.voltages[2], Voltage::Floating);
+    assert_eq!(settled.conflicts, 0);
+  }
+
+  #[test]
+  fn push_outputs_contend() {
+    let settled = settle("\
+: net type gnd y 1
+: net type vcc y 2
+: pin label 'x
+: pin label 'y
+: pin label 'a value not 'x
+: pin label 'b value not 'y
+: wire from 0 0 to 0 2
+: wire from 1 0 to 1 1
+: wire from 2 0 to 3 0
+", Family::Unknown);
+    assert_eq!(settled.voltages[2], Voltage::Unknown);
+    assert_eq!(settled.conflicts, 1);
+  }
+}
@@ -76,15 +76,26 @@ pub fn assert_is_within_bounds_unchecked(x: u16, y: u16, state: &CirnoState) ->
 }
 
 pub fn read_line() -> Result<String, io::Error> {
+  read_line_with_completion(&[])
+}
+
+/// Read a line like `read_line`, where pressing tab cycles through the candidates
+/// that start with what has been typed so far, either as a whole or after their last `/`.
+pub fn read_line_with_completion(candidates: &[String]) -> Result<String, io::Error> {
   let mut line = String::new();
+  // the matching candidates and the one currently shown, while tab is being pressed
+  let mut completion: Option<(Vec<&String>, usize)> = None;
   while let Ok(Event::Key(KeyEvent { code, modifiers: _, kind, state: _ })) = crossterm::event::read() {
     if !matches!(kind, KeyEventKind::Press) {
       continue;
     }
+    if code != KeyCode::Tab {
+      completion = None;
+    }
     match code {
       KeyCode::Enter => { break; },
       KeyCode::Backspace => {
-        if line.eq("") {
+        if line.is_empty() {
           break;
         }
         line.pop();
@@ -94,6 +105,25 @@ pub fn read_line() -> Result<String, io::Error> {
         line.push(c);
         execute!(stdout(), crossterm::style::Print(c))?;
       },
+      KeyCode::Tab => {
+        let (matches, index) = completion.get_or_insert_with(|| {
+          let prefix = line.as_str();
+          let matches: Vec<&String> = candidates
+            .iter()
+            .filter(|x| x.starts_with(prefix) || x.rsplit('/').next().is_some_and(|s| s.starts_with(prefix)))
+            .collect();
+          (matches, usize::MAX)
+        });
+        if matches.is_empty() {
+          continue;
+        }
+        *index = index.wrapping_add(1) % matches.len();
+        for _ in line.chars() {
+          backspace()?;
+        }
+        line = matches[*index].clone();
+        execute!(stdout(), crossterm::style::Print(&line))?;
+      },
       _ => {},
     }
   }